@@ -1,4 +1,5 @@
-use crate::data;
+use crate::data::Prefabs;
+use crate::enemy::EnemyType;
 use bevy::core::Timer;
 use bevy::ecs::Query;
 use bevy::prelude::*;
@@ -6,25 +7,25 @@ use bevy::sprite::TextureAtlasSprite;
 use bevy_rapier3d::physics::RigidBodyHandleComponent;
 use bevy_rapier3d::rapier::dynamics::RigidBodySet;
 use bevy_rapier3d::rapier::math::{AngVector, Rotation};
+use serde::{Deserialize, Serialize};
 
 const ANIMATION_SPEED: f32 = 0.5;
 
-#[derive(PartialEq, Eq, Copy, Clone, Hash)]
+#[derive(Deserialize, PartialEq, Eq, Copy, Clone, Hash, Debug)]
 pub enum AnimationState {
     Idle,
     Move,
     Attack,
 }
 
-#[derive(PartialEq, Eq, Copy, Clone, Hash)]
+#[derive(Deserialize, PartialEq, Eq, Copy, Clone, Hash, Debug)]
 pub enum UnitType {
     Wizard,
-    Enemy,
+    Enemy(EnemyType),
     Arrow,
 }
 
-#[derive(PartialEq, Eq, Copy, Clone, Hash)]
-#[allow(dead_code)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Copy, Clone, Hash, Debug)]
 pub enum CardinalDirection {
     North,
     South,
@@ -32,6 +33,40 @@ pub enum CardinalDirection {
     East,
 }
 
+impl CardinalDirection {
+    pub fn opposite(&self) -> CardinalDirection {
+        match self {
+            CardinalDirection::North => CardinalDirection::South,
+            CardinalDirection::South => CardinalDirection::North,
+            CardinalDirection::West => CardinalDirection::East,
+            CardinalDirection::East => CardinalDirection::West,
+        }
+    }
+
+    pub fn clockwise(&self) -> CardinalDirection {
+        match self {
+            CardinalDirection::North => CardinalDirection::East,
+            CardinalDirection::East => CardinalDirection::South,
+            CardinalDirection::South => CardinalDirection::West,
+            CardinalDirection::West => CardinalDirection::North,
+        }
+    }
+
+    pub fn counter_clockwise(&self) -> CardinalDirection {
+        self.clockwise().opposite()
+    }
+
+    /// Unit vector pointing in the direction, with north towards positive y.
+    pub fn to_vector(self) -> Vec2 {
+        match self {
+            CardinalDirection::North => Vec2::new(0., 1.),
+            CardinalDirection::South => Vec2::new(0., -1.),
+            CardinalDirection::West => Vec2::new(-1., 0.),
+            CardinalDirection::East => Vec2::new(1., 0.),
+        }
+    }
+}
+
 pub struct Animated {
     pub unit_type: UnitType,
     pub state: AnimationState,
@@ -57,8 +92,8 @@ pub struct AnimationBundle {
 }
 
 impl AnimationBundle {
-    pub fn new(unit_type: UnitType) -> Self {
-        let animation_info = data::get_animation_info(&unit_type, &AnimationState::Idle);
+    pub fn new(unit_type: UnitType, prefabs: &Prefabs) -> Self {
+        let animation_info = prefabs.animation(unit_type, AnimationState::Idle);
         AnimationBundle {
             animated: Animated::new(unit_type),
             timer: Timer::from_seconds(animation_info.durations[0], false),
@@ -67,6 +102,7 @@ impl AnimationBundle {
 }
 
 pub fn animate(
+    prefabs: Res<Prefabs>,
     mut rigid_body_set: ResMut<RigidBodySet>,
     mut query: Query<(
         &mut Timer,
@@ -77,7 +113,7 @@ pub fn animate(
 ) {
     for (mut timer, mut sprite, mut animated, rigid_body_handle) in query.iter_mut() {
         if timer.finished {
-            let mut animation_info = data::get_animation_info(&animated.unit_type, &animated.state);
+            let mut animation_info = prefabs.animation(animated.unit_type, animated.state);
 
             let next_index = animated.animation_index + 1;
             if next_index >= animation_info.length {
@@ -86,8 +122,7 @@ pub fn animate(
                 } else {
                     animated.animation_index = 0;
                     animated.state = AnimationState::Idle;
-                    animation_info =
-                        data::get_animation_info(&animated.unit_type, &AnimationState::Idle);
+                    animation_info = prefabs.animation(animated.unit_type, AnimationState::Idle);
                 }
             } else {
                 animated.animation_index = next_index;
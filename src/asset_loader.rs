@@ -7,6 +7,8 @@ use crate::biome::Biome;
 use crate::cursor::CursorState;
 use crate::data;
 use crate::data::{AssetGroup, AssetInfo, AssetType};
+use crate::display::{DisplayConfig, WorldCamera};
+use crate::enemy::ALL_ENEMY_TYPES;
 
 pub struct AssetLoaderPlugin;
 
@@ -148,6 +150,10 @@ impl AtlasHandles {
     fn assets_loaded(&self) -> bool {
         self.asset_loaded(AssetType::Builder)
             && self.asset_loaded(AssetType::Conveyor)
+            && self.asset_loaded(AssetType::Producer)
+            && self.asset_loaded(AssetType::ArrowTower)
+            && self.asset_loaded(AssetType::Wall)
+            && self.asset_loaded(AssetType::Wood)
             && self.asset_loaded(AssetType::Builder)
     }
 }
@@ -155,24 +161,33 @@ impl AtlasHandles {
 fn loader(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
+    display_config: Res<DisplayConfig>,
     mut map_sprite_handles: ResMut<SpriteHandles>,
 ) {
     map_sprite_handles.add_asset_group(AssetGroup::Biome, &asset_server);
     map_sprite_handles.add_asset_group(AssetGroup::Projectile, &asset_server);
 
     map_sprite_handles.add_asset(AssetType::Builder, &asset_server);
-    map_sprite_handles.add_asset(AssetType::Enemy, &asset_server);
+    for enemy_type in ALL_ENEMY_TYPES.iter() {
+        map_sprite_handles.add_asset(AssetType::Enemy(*enemy_type), &asset_server);
+    }
     map_sprite_handles.add_asset(AssetType::Conveyor, &asset_server);
+    map_sprite_handles.add_asset(AssetType::Producer, &asset_server);
+    map_sprite_handles.add_asset(AssetType::ArrowTower, &asset_server);
+    map_sprite_handles.add_asset(AssetType::Wall, &asset_server);
+    map_sprite_handles.add_asset(AssetType::Wood, &asset_server);
 
+    let camera_scale = display_config.camera_scale;
     let camera_entity = commands
         .spawn(Camera2dComponents {
             transform: Transform {
-                scale: Vec3::new(0.3, 0.3, 0.3),
+                scale: Vec3::new(camera_scale, camera_scale, camera_scale),
                 translation: Vec3::new(0., 0., 10.),
                 ..Default::default()
             },
             ..Default::default()
         })
+        .with(WorldCamera)
         .current_entity()
         .unwrap();
 
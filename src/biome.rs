@@ -1,7 +1,12 @@
-#[derive(Debug, Copy, Clone, PartialEq)]
-#[allow(dead_code)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Biome {
     Grassland,
     Desert,
     Rockland,
 }
+
+impl Biome {
+    pub const ALL: [Biome; 3] = [Biome::Grassland, Biome::Desert, Biome::Rockland];
+}
@@ -1,16 +1,25 @@
-use crate::animation::{Animated, AnimationState, UnitType};
+use crate::animation::{Animated, AnimationState};
 use crate::asset_loader::AtlasHandles;
-use crate::data;
-use crate::data::AssetType;
-use crate::projectile::Projectile;
+use crate::data::{AssetType, Prefabs};
+use crate::health::Health;
+use crate::inventory::{Inventory, ItemStack};
+use crate::item::Item;
+use crate::projectile::{spawn_projectiles, Projectile};
 use crate::weapon::Weapon;
+use crate::world_map::{tile_to_position, TileCoordinate};
 use bevy::prelude::*;
 use bevy_rapier3d::physics::RigidBodyHandleComponent;
 use bevy_rapier3d::rapier::dynamics::{RigidBodyBuilder, RigidBodySet};
 use bevy_rapier3d::rapier::math::{AngVector, Rotation};
 use bevy_rapier3d::rapier::na::{Isometry3, Vector3};
+use serde::{Deserialize, Serialize};
 
-#[derive(PartialEq, Eq, Copy, Clone, Hash, Debug)]
+pub const BUILDER_INVENTORY_SLOTS: usize = 20;
+
+// enough for a first stretch of belt and a couple of producers
+const STARTING_ITEMS: [(Item, u32); 2] = [(Item::Wood, 40), (Item::Stone, 20)];
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Copy, Clone, Hash, Debug)]
 pub enum BuilderMode {
     Construct,
     Combat,
@@ -22,6 +31,8 @@ pub struct Builder {
 
     // last aimed cursor location
     pub aim_location: Option<Vec4>,
+    // resource node being harvested, the attack animation swings at it instead of shooting
+    pub harvesting: Option<TileCoordinate>,
 }
 
 impl Builder {
@@ -30,6 +41,7 @@ impl Builder {
             name: String::from(name),
             mode: BuilderMode::Combat,
             aim_location: Option::None,
+            harvesting: Option::None,
         }
     }
 
@@ -54,9 +66,76 @@ impl Builder {
     }
 }
 
+/// What the builder carries into a new game.
+pub fn starting_inventory() -> Inventory {
+    let mut inventory = Inventory::new(BUILDER_INVENTORY_SLOTS);
+    for (item, count) in STARTING_ITEMS.iter() {
+        inventory.add(*item, *count);
+    }
+    inventory
+}
+
+/// Brings the builder back to the spawn point at full health once they die, minus the share of
+/// their items that dying costs.
+pub fn respawn_builder(
+    prefabs: Res<Prefabs>,
+    mut rigid_body_set: ResMut<RigidBodySet>,
+    mut builder_query: Query<(
+        &Builder,
+        &RigidBodyHandleComponent,
+        &mut Health,
+        &mut Inventory,
+    )>,
+) {
+    for (builder, builder_body_handle, mut health, mut inventory) in builder_query.iter_mut() {
+        if !health.is_dead() {
+            continue;
+        }
+        let builder_info = prefabs.builder();
+        println!("{} died", builder.name);
+        for stack in apply_death_penalty(&mut inventory, builder_info.death_penalty) {
+            println!("Lost {} {:?}", stack.count, stack.item);
+        }
+
+        let builder_body = rigid_body_set
+            .get_mut(builder_body_handle.handle())
+            .unwrap();
+        let spawn_position = tile_to_position(builder_info.spawn_tile).translation;
+        let builder_z = builder_body.position().translation.z;
+        builder_body.set_position(
+            Isometry3::translation(spawn_position.x(), spawn_position.y(), builder_z),
+            true,
+        );
+        builder_body.set_linvel(Vector3::zeros(), true);
+        health.restore(builder_info.respawn_invulnerable_seconds);
+    }
+}
+
+/// Takes the given share of each carried item, rounded down, returning what was taken.
+fn apply_death_penalty(inventory: &mut Inventory, penalty: f32) -> Vec<ItemStack> {
+    let mut totals: Vec<ItemStack> = Vec::new();
+    for stack in inventory.stacks() {
+        match totals.iter_mut().find(|total| total.item == stack.item) {
+            Some(total) => total.count += stack.count,
+            None => totals.push(*stack),
+        }
+    }
+
+    totals
+        .into_iter()
+        .map(|total| {
+            let lost = (total.count as f32 * penalty) as u32;
+            ItemStack::new(total.item, inventory.remove(total.item, lost))
+        })
+        .filter(|lost| lost.count > 0)
+        .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn produce_projectiles(
     mut commands: Commands,
     atlas_handles: Res<AtlasHandles>,
+    prefabs: Res<Prefabs>,
     rigid_body_set: Res<RigidBodySet>,
     animated: &Animated,
     builder_body_handle: &RigidBodyHandleComponent,
@@ -64,49 +143,44 @@ pub fn produce_projectiles(
     weapon: &Weapon,
 ) {
     if let Some(arrow_id) = atlas_handles.get_asset(AssetType::Arrow) {
-        if animated.state == AnimationState::Attack && animated.animation_index == 1 {
+        if animated.state == AnimationState::Attack
+            && animated.animation_index == 1
+            && builder.harvesting.is_none()
+        {
             let builder_body = rigid_body_set.get(builder_body_handle.handle()).unwrap();
 
-            let projectile = Projectile::arrow();
-            let projectile_positions =
-                compute_projectile_positions(builder_body.position(), builder, weapon, &projectile);
-
-            for (transform, body) in projectile_positions {
-                let arrow_atlas_handle = Handle::weak(arrow_id);
-                let projectile_timer = Timer::from_seconds((&projectile).ttl, false);
-
-                let arrow_entity = commands
-                    .spawn(SpriteSheetComponents {
-                        texture_atlas: arrow_atlas_handle,
-                        sprite: TextureAtlasSprite::new(0),
-                        transform,
-                        ..Default::default()
-                    })
-                    .with(projectile.clone())
-                    .with(projectile_timer)
-                    .current_entity()
-                    .unwrap();
-
-                let arrow_collider = data::get_collision_data(UnitType::Arrow)
-                    .user_data(arrow_entity.to_bits() as u128);
-
-                commands.insert(arrow_entity, (body, arrow_collider));
+            if let Some(aim_vector) = builder.to_aimed_location(builder_body.position()) {
+                let projectile = Projectile::arrow(&prefabs);
+                let projectile_positions = compute_projectile_positions(
+                    builder_body.position(),
+                    aim_vector,
+                    weapon,
+                    &projectile,
+                );
+                spawn_projectiles(
+                    &mut commands,
+                    arrow_id,
+                    &prefabs,
+                    &projectile,
+                    projectile_positions,
+                );
             }
         }
     }
 }
 
-fn compute_projectile_positions(
-    builder_position: &Isometry3<f32>,
-    builder: &Builder,
+/// Lays out the projectiles of one shot from `origin`, fanned out around `aim_vector`, the unit
+/// vector pointing at the target. Used by the builder and by towers alike.
+pub fn compute_projectile_positions(
+    origin: &Isometry3<f32>,
+    aim_vector: Vector3<f32>,
     weapon: &Weapon,
     projectile: &Projectile,
 ) -> Vec<(Transform, RigidBodyBuilder)> {
     let mut result = Vec::with_capacity(weapon.spread_range().len());
     for i in weapon.spread_range() {
-        let mut arrow_position = (*builder_position).clone();
+        let mut arrow_position = (*origin).clone();
 
-        let aim_vector = builder.to_aimed_location(builder_position).unwrap();
         let velocity_vector =
             aim_vector.clone() * (projectile.speed + weapon.projectile_launch_speed);
         let normal_vector = Vector3::new(-aim_vector.y, aim_vector.x, aim_vector.z);
@@ -210,16 +284,53 @@ mod tests {
         assert_eq!(velocity_z, body.linvel().z);
     }
 
+    #[test]
+    fn test_apply_death_penalty() {
+        let mut inventory = Inventory::new(4);
+        let stack_limit = Item::Wood.stack_limit();
+        inventory.add(Item::Wood, stack_limit + 10);
+        inventory.add(Item::Stone, 3);
+        inventory.add(Item::Arrow, 1);
+
+        let lost = apply_death_penalty(&mut inventory, 0.5);
+        // the share is taken of the total, not of each stack, and nobody loses half an arrow
+        assert_eq!(
+            vec![
+                ItemStack::new(Item::Wood, (stack_limit + 10) / 2),
+                ItemStack::new(Item::Stone, 1),
+            ],
+            lost
+        );
+        assert_eq!(
+            stack_limit + 10 - lost[0].count,
+            inventory.count(Item::Wood)
+        );
+        assert_eq!(2, inventory.count(Item::Stone));
+        assert_eq!(1, inventory.count(Item::Arrow));
+    }
+
     #[test]
     fn test_compute_projectile_positions() {
         let mut builder = Builder::new("tester");
         let builder_position = Isometry3::new(Vector3::zeros(), Vector3::zeros());
-        let weapon = Weapon::new(10., Some(ProjectileType::Arrow), 5, 100., 10.);
-        let projectile = Projectile::new(10, 10., true, 500.);
+        let weapon = Weapon {
+            size: 10.,
+            projectile_type: Some(ProjectileType::Arrow),
+            projectile_count: 5,
+            projectile_launch_speed: 100.,
+            projectile_spread: 10.,
+        };
+        let projectile = Projectile {
+            damage: 10,
+            ttl: 10.,
+            piercing: true,
+            speed: 500.,
+        };
 
         builder.aim_location.replace(Vec4::new(1., 0., 0., 0.));
+        let aim_vector = builder.to_aimed_location(&builder_position).unwrap();
         let projectile_positions =
-            compute_projectile_positions(&builder_position, &builder, &weapon, &projectile);
+            compute_projectile_positions(&builder_position, aim_vector, &weapon, &projectile);
 
         assert_eq!(5, projectile_positions.len());
 
@@ -1,40 +1,566 @@
+use std::fmt;
+
+use crate::animation::CardinalDirection;
 use crate::asset_loader::AtlasHandles;
 use crate::builder::{Builder, BuilderMode};
 use crate::cursor::CursorState;
-use crate::data::AssetType;
-use crate::world_map::WorldMap;
+use crate::data::{AssetType, Prefabs};
+use crate::global_constants::TILE_LENGTH;
+use crate::health::Health;
+use crate::input_bindings::InputBindings;
+use crate::inventory::{Inventory, ItemStack};
+use crate::logistics::Conveyor;
+use crate::production::Producer;
+use crate::resource_node::ResourceKind;
+use crate::structure_grid::StructureGrid;
+use crate::tower::Tower;
+use crate::world_map::{position_to_tile, tile_to_position, TileCoordinate, TileRegion, WorldMap};
 use bevy::prelude::*;
+use bevy_rapier3d::physics::RigidBodyHandleComponent;
+use bevy_rapier3d::rapier::dynamics::RigidBodySet;
+use serde::{Deserialize, Serialize};
+
+const STRUCTURE_Z: f32 = 2.;
+
+// in tiles from the builder, along either axis
+const BUILDER_REACH: i32 = 8;
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Copy, Clone, Hash, Debug)]
+pub enum StructureType {
+    Conveyor,
+    Producer,
+    ArrowTower,
+    // does nothing but stand in the way of enemies, who path around it
+    Wall,
+}
+
+pub const ALL_STRUCTURE_TYPES: [StructureType; 4] = [
+    StructureType::Conveyor,
+    StructureType::Producer,
+    StructureType::ArrowTower,
+    StructureType::Wall,
+];
+
+impl StructureType {
+    /// Width and height in tiles.
+    pub fn size(&self) -> (i32, i32) {
+        match self {
+            StructureType::Conveyor | StructureType::ArrowTower | StructureType::Wall => (1, 1),
+            StructureType::Producer => (2, 2),
+        }
+    }
+
+    pub fn asset_type(&self) -> AssetType {
+        match self {
+            StructureType::Conveyor => AssetType::Conveyor,
+            StructureType::Producer => AssetType::Producer,
+            StructureType::ArrowTower => AssetType::ArrowTower,
+            StructureType::Wall => AssetType::Wall,
+        }
+    }
 
+    /// Whether the structure's facing matters, and so is shown by turning its sprite.
+    pub fn is_directional(&self) -> bool {
+        match self {
+            StructureType::Conveyor => true,
+            StructureType::Producer | StructureType::ArrowTower | StructureType::Wall => false,
+        }
+    }
+
+    pub fn max_hp(&self) -> i32 {
+        match self {
+            StructureType::Conveyor => 20,
+            StructureType::Producer => 100,
+            StructureType::ArrowTower => 150,
+            StructureType::Wall => 300,
+        }
+    }
+}
+
+/// Why a structure can't be placed where the builder is pointing.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum PlacementError {
+    Occupied,
+    OutOfReach,
+    // part of the footprint is in a chunk that isn't loaded
+    UnknownTerrain,
+    // the builder is short of these items
+    MissingItems(ItemStack),
+    // a resource node that hasn't been harvested yet stands on the tile
+    Blocked(ResourceKind),
+}
+
+impl fmt::Display for PlacementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlacementError::Occupied => write!(f, "the tile is already taken"),
+            PlacementError::OutOfReach => write!(f, "it is out of the builder's reach"),
+            PlacementError::UnknownTerrain => write!(f, "the terrain there isn't loaded"),
+            PlacementError::MissingItems(missing) => {
+                write!(f, "it needs {} more {:?}", missing.count, missing.item)
+            }
+            PlacementError::Blocked(kind) => write!(f, "a {:?} is in the way", kind),
+        }
+    }
+}
+
+/// Selection, facing and drag state of the building in the builder's hand.
+pub struct PlacementState {
+    pub selected: StructureType,
+    pub facing: CardinalDirection,
+    // last tile placed on while the place action is held, and what was placed there
+    drag: Option<(TileCoordinate, Option<Entity>)>,
+}
+
+impl Default for PlacementState {
+    fn default() -> Self {
+        PlacementState {
+            selected: StructureType::Conveyor,
+            facing: CardinalDirection::East,
+            drag: Option::None,
+        }
+    }
+}
+
+/// A building placed on the map.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Structure {
+    pub structure_type: StructureType,
+    // bottom left tile of the structure's footprint
+    pub tile: TileCoordinate,
+    pub facing: CardinalDirection,
+}
+
+impl Structure {
+    pub fn new(
+        structure_type: StructureType,
+        tile: TileCoordinate,
+        facing: CardinalDirection,
+    ) -> Self {
+        Structure {
+            structure_type,
+            tile,
+            facing,
+        }
+    }
+
+    /// Tiles covered by the structure.
+    pub fn footprint(&self) -> TileRegion {
+        let (width, height) = self.structure_type.size();
+        TileRegion {
+            min: self.tile,
+            max: TileCoordinate(self.tile.0 + width - 1, self.tile.1 + height - 1),
+        }
+    }
+
+    /// Transform of the structure's sprite: centered on its footprint and turned to its facing.
+    pub fn sprite_transform(&self) -> Transform {
+        let center = self.center();
+        let mut transform =
+            Transform::from_translation(Vec3::new(center.x(), center.y(), STRUCTURE_Z));
+        if self.structure_type.is_directional() {
+            transform.rotation = facing_rotation(self.facing);
+        }
+        transform
+    }
+
+    /// World position of the center of the structure's footprint.
+    fn center(&self) -> Vec2 {
+        let (width, height) = self.structure_type.size();
+        let origin = tile_to_position(self.tile).translation;
+        let tile_length = TILE_LENGTH as f32;
+        Vec2::new(
+            origin.x() + (width - 1) as f32 * tile_length / 2.,
+            origin.y() + (height - 1) as f32 * tile_length / 2.,
+        )
+    }
+}
+
+/// Spawns the structure's entity and records it in the structure grid.
+///
+/// Returns `None` without spawning if any of its tiles is occupied, or if its texture isn't
+/// loaded yet.
+pub fn spawn_structure(
+    commands: &mut Commands,
+    atlas_handles: &AtlasHandles,
+    structure_grid: &mut StructureGrid,
+    structure: Structure,
+) -> Option<Entity> {
+    let footprint = structure.footprint();
+    if !structure_grid.is_free(&footprint) {
+        return Option::None;
+    }
+
+    let structure_type = structure.structure_type;
+    let atlas_id = atlas_handles.get_asset(structure_type.asset_type())?;
+    let facing = structure.facing;
+
+    commands
+        .spawn(SpriteSheetComponents {
+            texture_atlas: Handle::weak(atlas_id),
+            sprite: TextureAtlasSprite::new(0),
+            transform: structure.sprite_transform(),
+            ..Default::default()
+        })
+        .with(structure)
+        .with(Health::new(structure_type.max_hp()));
+    match structure_type {
+        StructureType::Conveyor => commands.with(Conveyor::new(facing)),
+        StructureType::Producer => commands.with(Producer::default()),
+        StructureType::ArrowTower => commands.with(Tower::default()),
+        StructureType::Wall => commands,
+    };
+    let structure_entity = commands.current_entity()?;
+    structure_grid
+        .insert(footprint, structure_entity)
+        .expect("the footprint was checked to be free");
+    Some(structure_entity)
+}
+
+/// Checks that the structure can be built where it stands, as seen from the builder's tile, and
+/// that the builder's inventory covers its cost.
+///
+/// Placement and its preview both go through here, so the preview never disagrees with what
+/// placing does.
+pub fn check_placement(
+    structure: &Structure,
+    cost: &[ItemStack],
+    builder_tile: TileCoordinate,
+    inventory: &Inventory,
+    structure_grid: &StructureGrid,
+    world_map: &WorldMap,
+) -> Result<(), PlacementError> {
+    let footprint = structure.footprint();
+    if !in_reach(builder_tile, &footprint) {
+        return Err(PlacementError::OutOfReach);
+    }
+    for tile_coordinate in footprint.coordinates() {
+        let tile = world_map
+            .get_tile(tile_coordinate)
+            .ok_or(PlacementError::UnknownTerrain)?;
+        if let Some(node) = tile.resource.filter(|node| !node.depleted) {
+            return Err(PlacementError::Blocked(node.kind));
+        }
+    }
+    if !structure_grid.is_free(&footprint) {
+        return Err(PlacementError::Occupied);
+    }
+    if let Some(missing) = inventory.missing(cost) {
+        return Err(PlacementError::MissingItems(missing));
+    }
+    Ok(())
+}
+
+pub fn in_reach(builder_tile: TileCoordinate, footprint: &TileRegion) -> bool {
+    let reach = TileRegion::around(builder_tile, BUILDER_REACH, BUILDER_REACH);
+    reach.contains(footprint.min) && reach.contains(footprint.max)
+}
+
+/// Tile under the cursor in world space, which is where the building in hand would go.
+pub fn cursor_tile(cursor_state: &CursorState) -> Option<TileCoordinate> {
+    cursor_state
+        .world_position
+        .map(|world_position| position_to_tile(world_position.x(), world_position.y()))
+}
+
+pub fn builder_tile(
+    rigid_body_set: &RigidBodySet,
+    builder_body_handle: &RigidBodyHandleComponent,
+) -> TileCoordinate {
+    let translation = rigid_body_set
+        .get(builder_body_handle.handle())
+        .unwrap()
+        .position()
+        .translation;
+    position_to_tile(translation.x, translation.y)
+}
+
+/// Sprites are drawn facing east, so they are turned towards the structure's facing.
+fn facing_rotation(facing: CardinalDirection) -> Quat {
+    let direction = facing.to_vector();
+    Quat::from_rotation_z(direction.y().atan2(direction.x()))
+}
+
+/// Direction of the dominant axis of a drag between two tiles.
+fn drag_direction(from: TileCoordinate, to: TileCoordinate) -> CardinalDirection {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    if dx.abs() >= dy.abs() {
+        if dx >= 0 {
+            CardinalDirection::East
+        } else {
+            CardinalDirection::West
+        }
+    } else if dy > 0 {
+        CardinalDirection::North
+    } else {
+        CardinalDirection::South
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn place_object(
     mut commands: Commands,
-    mouse_button_input: Res<Input<MouseButton>>,
+    input_bindings: Res<InputBindings>,
     cursor_state: Res<CursorState>,
-    world_map: Res<WorldMap>,
     atlas_handles: Res<AtlasHandles>,
-    builder: &Builder,
+    prefabs: Res<Prefabs>,
+    mut structure_grid: ResMut<StructureGrid>,
+    mut placement_state: ResMut<PlacementState>,
+    world_map: Res<WorldMap>,
+    rigid_body_set: Res<RigidBodySet>,
+    mut builder_query: Query<(&Builder, &RigidBodyHandleComponent, &mut Inventory)>,
+    mut structure_query: Query<(&mut Structure, &mut Transform)>,
+    mut conveyor_query: Query<&mut Conveyor>,
 ) {
-    if builder.mode != BuilderMode::Construct {
+    let (builder_tile, mut inventory) = match builder_query.iter_mut().next() {
+        Some((builder, builder_body_handle, inventory))
+            if builder.mode == BuilderMode::Construct =>
+        {
+            (
+                builder_tile(&rigid_body_set, builder_body_handle),
+                inventory,
+            )
+        }
+        _ => {
+            placement_state.drag = Option::None;
+            return;
+        }
+    };
+
+    if input_bindings.action_just_pressed("rotate_back") {
+        placement_state.facing = placement_state.facing.counter_clockwise();
+    } else if input_bindings.action_just_pressed("rotate") {
+        placement_state.facing = placement_state.facing.clockwise();
+    }
+
+    if !input_bindings.action_pressed("place") {
+        placement_state.drag = Option::None;
         return;
     }
-    if mouse_button_input.pressed(MouseButton::Left) {
-        if let Some(cursor_coordinates) = cursor_state.cursor_position {
-            if let Some(conveyor_id) = atlas_handles.get_asset(AssetType::Conveyor) {
-                let map_tile =
-                    world_map.position_to_tile(cursor_coordinates.x(), cursor_coordinates.y());
-                let tile_position = world_map.tile_to_position(map_tile.0, map_tile.1);
-                let conveyor_atlas_handle = Handle::weak(conveyor_id);
-
-                commands.spawn(SpriteSheetComponents {
-                    texture_atlas: conveyor_atlas_handle,
-                    sprite: TextureAtlasSprite::new(0),
-                    transform: Transform::from_translation(Vec3::new(
-                        tile_position.translation.x(),
-                        tile_position.translation.y(),
-                        2.,
-                    )),
-                    ..Default::default()
-                });
+    if let Some(map_tile) = cursor_tile(&cursor_state) {
+        if let Some((previous_tile, previous_entity)) = placement_state.drag {
+            if previous_tile == map_tile {
+                return;
+            }
+            // dragging lays the belt along the drag, including the piece placed last
+            let facing = drag_direction(previous_tile, map_tile);
+            placement_state.facing = facing;
+            if let Some(previous_entity) = previous_entity {
+                let mut rotation = Option::None;
+                if let Ok(mut structure) =
+                    structure_query.get_component_mut::<Structure>(previous_entity)
+                {
+                    structure.facing = facing;
+                    rotation.replace(structure.sprite_transform().rotation);
+                }
+                if let (Some(rotation), Ok(mut transform)) = (
+                    rotation,
+                    structure_query.get_component_mut::<Transform>(previous_entity),
+                ) {
+                    transform.rotation = rotation;
+                }
+                if let Ok(mut conveyor) =
+                    conveyor_query.get_component_mut::<Conveyor>(previous_entity)
+                {
+                    conveyor.direction = facing;
+                }
             }
         }
+
+        let structure = Structure::new(placement_state.selected, map_tile, placement_state.facing);
+        let cost = prefabs.cost(structure.structure_type);
+        let placed_entity = match check_placement(
+            &structure,
+            cost,
+            builder_tile,
+            &inventory,
+            &structure_grid,
+            &world_map,
+        ) {
+            Ok(()) => {
+                let placed_entity = spawn_structure(
+                    &mut commands,
+                    &atlas_handles,
+                    &mut structure_grid,
+                    structure,
+                );
+                if placed_entity.is_some() {
+                    inventory.remove_all(cost);
+                }
+                placed_entity
+            }
+            Err(placement_error) => {
+                // only reported on click, not for every tile of a drag, unless the drag ran out of
+                // items and stopped laying belt
+                let ran_out = matches!(placement_error, PlacementError::MissingItems(_));
+                if input_bindings.action_just_pressed("place") || ran_out {
+                    println!(
+                        "Cannot place {:?} here: {}",
+                        placement_state.selected, placement_error
+                    );
+                }
+                Option::None
+            }
+        };
+        placement_state.drag = Some((map_tile, placed_entity));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::Item;
+    use crate::resource_node::ResourceNode;
+    use crate::world_generator::WorldGenerator;
+
+    #[test]
+    fn test_footprint() {
+        let structure = Structure::new(
+            StructureType::Conveyor,
+            TileCoordinate(-2, 3),
+            CardinalDirection::North,
+        );
+        assert_eq!(
+            TileRegion {
+                min: TileCoordinate(-2, 3),
+                max: TileCoordinate(-2, 3),
+            },
+            structure.footprint()
+        );
+        let position = tile_to_position(TileCoordinate(-2, 3)).translation;
+        assert_eq!(Vec2::new(position.x(), position.y()), structure.center());
+    }
+
+    #[test]
+    fn test_drag_direction() {
+        let origin = TileCoordinate(0, 0);
+        assert_eq!(
+            CardinalDirection::East,
+            drag_direction(origin, TileCoordinate(1, 0))
+        );
+        assert_eq!(
+            CardinalDirection::West,
+            drag_direction(origin, TileCoordinate(-3, 1))
+        );
+        assert_eq!(
+            CardinalDirection::North,
+            drag_direction(origin, TileCoordinate(1, 2))
+        );
+        assert_eq!(
+            CardinalDirection::South,
+            drag_direction(origin, TileCoordinate(0, -1))
+        );
+    }
+
+    #[test]
+    fn test_check_placement() {
+        let world_generator = WorldGenerator::new(3, 48., 0., 0.);
+        let mut world_map = WorldMap::new();
+        world_map.update_loaded_chunks(TileCoordinate(0, 0), &world_generator);
+        let mut structure_grid = StructureGrid::default();
+        let builder_tile = TileCoordinate(0, 0);
+        let cost = [ItemStack::new(Item::Wood, 2)];
+        let mut inventory = Inventory::new(1);
+        inventory.add(Item::Wood, 2);
+        let conveyor_at = |x, y| {
+            Structure::new(
+                StructureType::Conveyor,
+                TileCoordinate(x, y),
+                CardinalDirection::East,
+            )
+        };
+
+        assert_eq!(
+            Ok(()),
+            check_placement(
+                &conveyor_at(2, -3),
+                &cost,
+                builder_tile,
+                &inventory,
+                &structure_grid,
+                &world_map
+            )
+        );
+        assert_eq!(
+            Err(PlacementError::OutOfReach),
+            check_placement(
+                &conveyor_at(BUILDER_REACH + 1, 0),
+                &cost,
+                builder_tile,
+                &inventory,
+                &structure_grid,
+                &world_map
+            )
+        );
+        assert_eq!(
+            Err(PlacementError::UnknownTerrain),
+            check_placement(
+                &conveyor_at(500, 0),
+                &cost,
+                TileCoordinate(500, 0),
+                &inventory,
+                &structure_grid,
+                &world_map
+            )
+        );
+
+        world_map
+            .get_tile_mut(TileCoordinate(1, 1))
+            .unwrap()
+            .resource = Some(ResourceNode::new(ResourceKind::Tree));
+        assert_eq!(
+            Err(PlacementError::Blocked(ResourceKind::Tree)),
+            check_placement(
+                &conveyor_at(1, 1),
+                &cost,
+                builder_tile,
+                &inventory,
+                &structure_grid,
+                &world_map
+            )
+        );
+        // nothing is left in the way once the node is harvested
+        let mut node = ResourceNode::new(ResourceKind::Tree);
+        node.depleted = true;
+        world_map.set_resource_node(TileCoordinate(1, 1), node);
+        assert_eq!(
+            Ok(()),
+            check_placement(
+                &conveyor_at(1, 1),
+                &cost,
+                builder_tile,
+                &inventory,
+                &structure_grid,
+                &world_map
+            )
+        );
+
+        inventory.remove(Item::Wood, 1);
+        assert_eq!(
+            Err(PlacementError::MissingItems(ItemStack::new(Item::Wood, 1))),
+            check_placement(
+                &conveyor_at(2, -3),
+                &cost,
+                builder_tile,
+                &inventory,
+                &structure_grid,
+                &world_map
+            )
+        );
+
+        structure_grid
+            .insert(conveyor_at(2, -3).footprint(), Entity::from_bits(1))
+            .unwrap();
+        assert_eq!(
+            Err(PlacementError::Occupied),
+            check_placement(
+                &conveyor_at(2, -3),
+                &cost,
+                builder_tile,
+                &inventory,
+                &structure_grid,
+                &world_map
+            )
+        );
     }
 }
@@ -3,7 +3,8 @@ use bevy::prelude::*;
 use crate::animation::{Animated, AnimationState, CardinalDirection};
 use crate::builder::{Builder, BuilderMode};
 use crate::cursor::CursorState;
-use bevy::render::camera::Camera;
+use crate::display::WorldCamera;
+use crate::input_bindings::InputBindings;
 use bevy_rapier3d::physics::RigidBodyHandleComponent;
 use bevy_rapier3d::rapier::dynamics::RigidBodySet;
 use bevy_rapier3d::rapier::math::Vector;
@@ -11,8 +12,7 @@ use bevy_rapier3d::rapier::math::Vector;
 const WIZARD_SPEED: f32 = 100.;
 
 pub fn control_builder(
-    keyboard_input: Res<Input<KeyCode>>,
-    mouse_button_input: Res<Input<MouseButton>>,
+    input_bindings: Res<InputBindings>,
     mut rigid_body_set: ResMut<RigidBodySet>,
     cursor_state: Res<CursorState>,
     mut query_builder: Query<(
@@ -21,7 +21,7 @@ pub fn control_builder(
         &mut Animated,
         &RigidBodyHandleComponent,
     )>,
-    mut query_camera: Query<(&Camera, &mut Transform)>,
+    mut query_camera: Query<(&WorldCamera, &mut Transform)>,
 ) {
     let query_builder_iterator = &mut query_builder.iter_mut();
     let query_camera_iterator = &mut query_camera.iter_mut();
@@ -33,12 +33,10 @@ pub fn control_builder(
             .get_mut(builder_body_handle.handle())
             .unwrap();
         if let Some((_camera, mut camera_transform)) = query_camera_iterator.next() {
-            let press_up = keyboard_input.pressed(KeyCode::W);
-            let press_down = keyboard_input.pressed(KeyCode::S);
-            let press_left = keyboard_input.pressed(KeyCode::A);
-            let press_right = keyboard_input.pressed(KeyCode::D);
+            let horizontal = input_bindings.axis_value("horizontal");
+            let vertical = input_bindings.axis_value("vertical");
 
-            if press_up || press_down || press_left || press_right {
+            if horizontal != 0. || vertical != 0. {
                 if animated.state == AnimationState::Idle {
                     animated.state = AnimationState::Move;
                     animated.animation_index = 0;
@@ -46,20 +44,11 @@ pub fn control_builder(
                     builder_timer.finished = true;
                 }
 
-                let mut x_speed = 0.;
-                let mut y_speed = 0.;
-                if press_up {
-                    y_speed = WIZARD_SPEED;
-                }
-                if press_down {
-                    y_speed = -WIZARD_SPEED;
-                }
-                if press_left {
-                    x_speed = -WIZARD_SPEED;
+                let x_speed = horizontal * WIZARD_SPEED;
+                let y_speed = vertical * WIZARD_SPEED;
+                if horizontal < 0. {
                     animated.facing = CardinalDirection::West;
-                }
-                if press_right {
-                    x_speed = WIZARD_SPEED;
+                } else if horizontal > 0. {
                     animated.facing = CardinalDirection::East;
                 }
                 builder_body.set_linvel(Vector::new(x_speed, y_speed, 0.), true);
@@ -72,13 +61,13 @@ pub fn control_builder(
         }
 
         // toggle build mode
-        if mouse_button_input.just_released(MouseButton::Right) {
+        if input_bindings.action_just_released("toggle_mode") {
             builder.toggle_mode();
             println!("Setting builder mode: {:?}", builder.mode)
         }
 
         // fire projectiles
-        if mouse_button_input.pressed(MouseButton::Left)
+        if input_bindings.action_pressed("attack")
             && builder.mode == BuilderMode::Combat
             && animated.state != AnimationState::Attack
         {
@@ -1,4 +1,6 @@
 use crate::enemy::Enemy;
+use crate::enemy_registry::EnemyRegistry;
+use crate::health::Health;
 use crate::projectile::Projectile;
 use bevy::prelude::*;
 use bevy_rapier3d::physics::EventQueue;
@@ -8,7 +10,8 @@ pub fn examine_collisions(
     mut commands: Commands,
     events: ResMut<EventQueue>,
     collider_set: Res<ColliderSet>,
-    mut enemy_query: Query<&mut Enemy>,
+    mut enemy_registry: ResMut<EnemyRegistry>,
+    mut enemy_query: Query<(&Enemy, &mut Health)>,
     projectile_query: Query<&Projectile>,
 ) {
     while let Ok(contact_event) = events.contact_events.pop() {
@@ -35,17 +38,16 @@ pub fn examine_collisions(
 
             if let Some(enemy_entity) = maybe_enemy_entity {
                 if let Some(projectile_entity) = maybe_projectile_entity {
-                    let mut enemy = enemy_query
-                        .get_component_mut::<Enemy>(enemy_entity)
-                        .unwrap();
+                    let (enemy, mut health) = enemy_query.get_mut(enemy_entity).unwrap();
                     let projectile = projectile_query
                         .get_component::<Projectile>(projectile_entity)
                         .unwrap();
-                    enemy.take_damage(projectile.damage);
-                    if enemy.hp <= 0 {
+                    health.hit(projectile.damage, 0.);
+                    if health.is_dead() {
+                        enemy_registry.remove(enemy.id);
                         commands.despawn(enemy_entity);
                     } else {
-                        println!("enemy hp {}", enemy.hp);
+                        println!("{} hp {}", enemy.name, health.hp);
                     }
                     commands.despawn(projectile_entity);
                 }
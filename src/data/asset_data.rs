@@ -1,4 +1,5 @@
 use crate::biome::Biome;
+use crate::enemy::{EnemyType, ALL_ENEMY_TYPES};
 use crate::global_constants::TILE_LENGTH;
 use bevy::math::Vec2;
 
@@ -38,8 +39,12 @@ impl AssetGroupInfo {
 pub enum AssetType {
     Arrow,
     Builder,
-    Enemy,
+    Enemy(EnemyType),
     Conveyor,
+    Producer,
+    ArrowTower,
+    Wall,
+    Wood,
     Grassland,
     Desert,
     Rockland,
@@ -70,7 +75,20 @@ pub fn all_asset_groups() -> Vec<AssetGroup> {
 }
 
 pub fn all_base_assets() -> Vec<AssetType> {
-    vec![AssetType::Builder, AssetType::Enemy, AssetType::Conveyor]
+    let mut base_assets = vec![
+        AssetType::Builder,
+        AssetType::Conveyor,
+        AssetType::Producer,
+        AssetType::ArrowTower,
+        AssetType::Wall,
+        AssetType::Wood,
+    ];
+    base_assets.extend(
+        ALL_ENEMY_TYPES
+            .iter()
+            .map(|enemy_type| AssetType::Enemy(*enemy_type)),
+    );
+    base_assets
 }
 
 fn grassland_asset_info() -> AssetInfo {
@@ -118,8 +136,13 @@ fn builder_asset_info() -> AssetInfo {
     )
 }
 
-fn enemy_asset_info() -> AssetInfo {
-    AssetInfo::new(String::from("texture/enemy.png"), default_tile_size(), 7, 3)
+fn enemy_asset_info(enemy_type: EnemyType) -> AssetInfo {
+    let sprite_file = match enemy_type {
+        EnemyType::Goblin => "texture/enemy/goblin.png",
+        EnemyType::Scorpion => "texture/enemy/scorpion.png",
+        EnemyType::Golem => "texture/enemy/golem.png",
+    };
+    AssetInfo::new(String::from(sprite_file), default_tile_size(), 7, 3)
 }
 
 fn conveyor_asset_info() -> AssetInfo {
@@ -131,12 +154,42 @@ fn conveyor_asset_info() -> AssetInfo {
     )
 }
 
+fn producer_asset_info() -> AssetInfo {
+    AssetInfo::new(
+        String::from("texture/producer.png"),
+        Vec2::new(32., 32.),
+        1,
+        1,
+    )
+}
+
+fn arrow_tower_asset_info() -> AssetInfo {
+    AssetInfo::new(
+        String::from("texture/arrow_tower.png"),
+        default_tile_size(),
+        1,
+        1,
+    )
+}
+
+fn wall_asset_info() -> AssetInfo {
+    AssetInfo::new(String::from("texture/wall.png"), default_tile_size(), 1, 1)
+}
+
+fn wood_asset_info() -> AssetInfo {
+    AssetInfo::new(String::from("texture/wood.png"), Vec2::new(4., 4.), 1, 1)
+}
+
 pub fn get_asset_info(asset_type: AssetType) -> AssetInfo {
     match asset_type {
         AssetType::Builder => builder_asset_info(),
-        AssetType::Enemy => enemy_asset_info(),
+        AssetType::Enemy(enemy_type) => enemy_asset_info(enemy_type),
         AssetType::Arrow => arrow_asset_info(),
         AssetType::Conveyor => conveyor_asset_info(),
+        AssetType::Producer => producer_asset_info(),
+        AssetType::ArrowTower => arrow_tower_asset_info(),
+        AssetType::Wall => wall_asset_info(),
+        AssetType::Wood => wood_asset_info(),
         AssetType::Grassland => grassland_asset_info(),
         AssetType::Desert => desert_asset_info(),
         AssetType::Rockland => rocklands_asset_info(),
@@ -160,6 +213,50 @@ pub fn get_asset_group_info(asset_group_type: AssetGroup) -> AssetGroupInfo {
     }
 }
 
+/// Relative weight of each sprite in a biome's atlas, so rare decorations can show up less often
+/// than plain ground. Must have one entry per atlas sprite.
+pub fn get_biome_variant_weights(biome: Biome) -> Vec<u32> {
+    match biome {
+        Biome::Grassland => vec![10, 10, 4, 1],
+        Biome::Desert => vec![10, 6, 3, 1],
+        Biome::Rockland => vec![8, 8, 4, 2],
+    }
+}
+
 pub fn get_asset_sprite_path(asset_type: AssetType) -> String {
     get_asset_info(asset_type).sprite_file
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::path::Path;
+
+    #[test]
+    fn test_biome_variant_weights_match_atlases() {
+        for biome in Biome::ALL.iter() {
+            let asset_info = get_asset_info(AssetType::from(*biome));
+            assert_eq!(
+                asset_info.columns * asset_info.rows,
+                get_biome_variant_weights(*biome).len(),
+                "{:?}",
+                biome
+            );
+        }
+    }
+
+    #[test]
+    fn test_enemy_types_have_their_own_sheets() {
+        let mut sprite_files = HashSet::new();
+        for enemy_type in ALL_ENEMY_TYPES.iter() {
+            let sprite_file = get_asset_sprite_path(AssetType::Enemy(*enemy_type));
+            assert!(
+                Path::new("assets").join(&sprite_file).exists(),
+                "{}",
+                sprite_file
+            );
+            assert!(sprite_files.insert(sprite_file));
+        }
+    }
+}
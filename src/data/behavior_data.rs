@@ -0,0 +1,43 @@
+use serde::Deserialize;
+
+use crate::enemy::{ENEMY_ATTACK_DISTANCE, ENEMY_SEARCH_DISTANCE};
+
+/// When an enemy notices, attacks, gives up on and runs from the builder. Missing fields fall back
+/// to the defaults every enemy used before behaviors were tunable.
+#[derive(Deserialize, Copy, Clone, Debug)]
+#[serde(default)]
+pub struct BehaviorInfo {
+    // in pixels from the enemy
+    pub search_distance: f32,
+    pub attack_distance: f32,
+    // in pixels from the enemy's home, beyond which it stops chasing and walks back
+    pub leash_distance: f32,
+    // share of its health left at which the enemy runs away, 0 to never flee
+    pub flee_health: f32,
+    pub seconds_per_attack: f32,
+    pub attack_damage: i32,
+    // frame of the attack animation on which the blow lands
+    pub attack_frame: u32,
+    // time spent standing still before wandering off, and wandering before standing still
+    pub idle_seconds: f32,
+    pub wander_seconds: f32,
+    // in tiles around the enemy's home
+    pub wander_radius: i32,
+}
+
+impl Default for BehaviorInfo {
+    fn default() -> Self {
+        BehaviorInfo {
+            search_distance: ENEMY_SEARCH_DISTANCE.sqrt(),
+            attack_distance: ENEMY_ATTACK_DISTANCE.sqrt(),
+            leash_distance: 240.,
+            flee_health: 0.,
+            seconds_per_attack: 1.,
+            attack_damage: 10,
+            attack_frame: 2,
+            idle_seconds: 3.,
+            wander_seconds: 2.,
+            wander_radius: 3,
+        }
+    }
+}
@@ -0,0 +1,17 @@
+use serde::Deserialize;
+
+use crate::world_map::TileCoordinate;
+
+/// How tough the builder is and what dying costs them.
+#[derive(Deserialize, Copy, Clone, Debug)]
+pub struct BuilderInfo {
+    pub max_hp: i32,
+    // hits are ignored for this long after one lands
+    pub invulnerable_seconds: f32,
+    // where the builder starts the game and comes back after dying
+    pub spawn_tile: TileCoordinate,
+    // share of each carried item lost on death
+    pub death_penalty: f32,
+    // hits are ignored for this long after coming back
+    pub respawn_invulnerable_seconds: f32,
+}
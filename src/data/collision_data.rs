@@ -1,22 +1,15 @@
-use crate::animation::UnitType;
 use bevy_rapier3d::rapier::geometry::ColliderBuilder;
+use serde::Deserialize;
 
-fn wizard_collision_data() -> ColliderBuilder {
-    ColliderBuilder::cuboid(5., 7., 8.)
+#[derive(Deserialize, Copy, Clone, Debug)]
+pub enum ColliderInfo {
+    Cuboid { hx: f32, hy: f32, hz: f32 },
 }
 
-fn enemy_collision_data() -> ColliderBuilder {
-    ColliderBuilder::cuboid(5., 7., 8.)
-}
-
-fn arrow_collision_data() -> ColliderBuilder {
-    ColliderBuilder::cuboid(4., 2., 8.)
-}
-
-pub fn get_collision_data(unit_type: UnitType) -> ColliderBuilder {
-    match unit_type {
-        UnitType::Wizard => wizard_collision_data(),
-        UnitType::Enemy => enemy_collision_data(),
-        UnitType::Arrow => arrow_collision_data(),
+impl ColliderInfo {
+    pub fn to_builder(self) -> ColliderBuilder {
+        match self {
+            ColliderInfo::Cuboid { hx, hy, hz } => ColliderBuilder::cuboid(hx, hy, hz),
+        }
     }
 }
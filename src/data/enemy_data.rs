@@ -0,0 +1,15 @@
+use serde::Deserialize;
+
+use crate::enemy::EnemyType;
+
+/// Stats of a kind of enemy. How it fights is in its behavior prefab.
+#[derive(Deserialize, Clone, Debug)]
+pub struct EnemyInfo {
+    pub name: String,
+    pub max_hp: i32,
+    // in pixels per second
+    pub speed: f32,
+}
+
+/// Which enemies a biome spawns, each with a relative weight.
+pub type SpawnTable = Vec<(EnemyType, u32)>;
@@ -0,0 +1,563 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy_rapier3d::rapier::geometry::ColliderBuilder;
+use serde::de::DeserializeOwned;
+
+use crate::animation::{AnimationState, UnitType};
+use crate::biome::Biome;
+use crate::construction::{StructureType, ALL_STRUCTURE_TYPES};
+use crate::data::animation_data::AnimationInfo;
+use crate::data::behavior_data::BehaviorInfo;
+use crate::data::builder_data::BuilderInfo;
+use crate::data::collision_data::ColliderInfo;
+use crate::data::enemy_data::{EnemyInfo, SpawnTable};
+use crate::data::production_data::ProductionInfo;
+use crate::data::resource_data::ResourceInfo;
+use crate::data::tower_data::TowerInfo;
+use crate::data::wave_data::WaveSchedule;
+use crate::enemy::{EnemyType, ALL_ENEMY_TYPES};
+use crate::inventory::ItemStack;
+use crate::projectile::{Projectile, ProjectileType};
+use crate::resource_node::{ResourceKind, ALL_RESOURCE_KINDS};
+use crate::weapon::{Weapon, WeaponType};
+use crate::world_generator::weighted_choice;
+
+pub const PREFAB_DIRECTORY: &str = "assets/prefabs";
+
+const WEAPONS_FILE: &str = "weapons.ron";
+const PROJECTILES_FILE: &str = "projectiles.ron";
+const ANIMATIONS_FILE: &str = "animations.ron";
+const COLLIDERS_FILE: &str = "colliders.ron";
+const PRODUCERS_FILE: &str = "producers.ron";
+const TOWERS_FILE: &str = "towers.ron";
+const COSTS_FILE: &str = "costs.ron";
+const RESOURCES_FILE: &str = "resources.ron";
+const ENEMIES_FILE: &str = "enemies.ron";
+const ENEMY_SPAWNS_FILE: &str = "enemy_spawns.ron";
+const BEHAVIORS_FILE: &str = "behaviors.ron";
+const BUILDER_FILE: &str = "builder.ron";
+const WAVES_FILE: &str = "waves.ron";
+
+const ALL_WEAPON_TYPES: [WeaponType; 2] = [WeaponType::MagicBow, WeaponType::Crossbow];
+const ALL_PROJECTILE_TYPES: [ProjectileType; 1] = [ProjectileType::Arrow];
+const ALL_UNIT_TYPES: [UnitType; 5] = [
+    UnitType::Wizard,
+    UnitType::Enemy(EnemyType::Goblin),
+    UnitType::Enemy(EnemyType::Scorpion),
+    UnitType::Enemy(EnemyType::Golem),
+    UnitType::Arrow,
+];
+const ALL_TOWER_TYPES: [StructureType; 1] = [StructureType::ArrowTower];
+const ALL_ANIMATION_STATES: [AnimationState; 3] = [
+    AnimationState::Idle,
+    AnimationState::Move,
+    AnimationState::Attack,
+];
+
+#[derive(Debug)]
+pub enum PrefabError {
+    Io(PathBuf, std::io::Error),
+    Malformed(PathBuf, ron::Error),
+    MissingEntry(PathBuf, String),
+    InvalidEntry(PathBuf, String, &'static str),
+}
+
+impl fmt::Display for PrefabError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrefabError::Io(path, err) => {
+                write!(f, "could not read {}: {}", path.display(), err)
+            }
+            PrefabError::Malformed(path, err) => {
+                write!(f, "malformed prefab file {}: {}", path.display(), err)
+            }
+            PrefabError::MissingEntry(path, key) => {
+                write!(f, "{} has no entry for {}", path.display(), key)
+            }
+            PrefabError::InvalidEntry(path, key, reason) => {
+                write!(
+                    f,
+                    "{} has an invalid entry for {}: {}",
+                    path.display(),
+                    key,
+                    reason
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for PrefabError {}
+
+/// Designer-tunable unit and building data, loaded from the RON files in `assets/prefabs`.
+///
+/// Every weapon, projectile, unit type, enemy type, biome, tower, structure and resource kind is
+/// checked for an entry on load, so lookups never fail.
+pub struct Prefabs {
+    weapons: HashMap<WeaponType, Weapon>,
+    projectiles: HashMap<ProjectileType, Projectile>,
+    animations: HashMap<UnitType, HashMap<AnimationState, AnimationInfo>>,
+    colliders: HashMap<UnitType, ColliderInfo>,
+    productions: HashMap<Biome, ProductionInfo>,
+    towers: HashMap<StructureType, TowerInfo>,
+    costs: HashMap<StructureType, Vec<ItemStack>>,
+    resources: HashMap<ResourceKind, ResourceInfo>,
+    enemies: HashMap<EnemyType, EnemyInfo>,
+    enemy_spawns: HashMap<Biome, SpawnTable>,
+    behaviors: HashMap<EnemyType, BehaviorInfo>,
+    builder: BuilderInfo,
+    waves: WaveSchedule,
+}
+
+impl Prefabs {
+    pub fn load<P: AsRef<Path>>(directory: P) -> Result<Prefabs, PrefabError> {
+        let directory = directory.as_ref();
+
+        let weapons = load_entries(&directory.join(WEAPONS_FILE), &ALL_WEAPON_TYPES, no_problem)?;
+        let projectiles = load_entries(
+            &directory.join(PROJECTILES_FILE),
+            &ALL_PROJECTILE_TYPES,
+            no_problem,
+        )?;
+        let colliders = load_entries(&directory.join(COLLIDERS_FILE), &ALL_UNIT_TYPES, no_problem)?;
+        let animations = load_entries(
+            &directory.join(ANIMATIONS_FILE),
+            &ALL_UNIT_TYPES,
+            animation_problem,
+        )?;
+        let productions = load_entries(
+            &directory.join(PRODUCERS_FILE),
+            &Biome::ALL,
+            production_problem,
+        )?;
+        let towers = load_entries(
+            &directory.join(TOWERS_FILE),
+            &ALL_TOWER_TYPES,
+            tower_problem,
+        )?;
+        let costs = load_entries(
+            &directory.join(COSTS_FILE),
+            &ALL_STRUCTURE_TYPES,
+            cost_problem,
+        )?;
+        let resources = load_entries(
+            &directory.join(RESOURCES_FILE),
+            &ALL_RESOURCE_KINDS,
+            resource_problem,
+        )?;
+        let enemies = load_entries(
+            &directory.join(ENEMIES_FILE),
+            &ALL_ENEMY_TYPES,
+            enemy_problem,
+        )?;
+        let enemy_spawns = load_entries(
+            &directory.join(ENEMY_SPAWNS_FILE),
+            &Biome::ALL,
+            enemy_spawn_problem,
+        )?;
+        let behaviors = load_entries(
+            &directory.join(BEHAVIORS_FILE),
+            &ALL_ENEMY_TYPES,
+            |enemy_type, behavior_info| behavior_problem(enemy_type, behavior_info, &animations),
+        )?;
+        let builder = load_file(
+            &directory.join(BUILDER_FILE),
+            "the builder",
+            builder_problem,
+        )?;
+        let waves = load_file(
+            &directory.join(WAVES_FILE),
+            "the wave schedule",
+            wave_problem,
+        )?;
+
+        Ok(Prefabs {
+            weapons,
+            projectiles,
+            animations,
+            colliders,
+            productions,
+            towers,
+            costs,
+            resources,
+            enemies,
+            enemy_spawns,
+            behaviors,
+            builder,
+            waves,
+        })
+    }
+
+    pub fn weapon(&self, weapon_type: WeaponType) -> Weapon {
+        self.weapons[&weapon_type].clone()
+    }
+
+    pub fn projectile(&self, projectile_type: ProjectileType) -> Projectile {
+        self.projectiles[&projectile_type].clone()
+    }
+
+    pub fn animation(&self, unit_type: UnitType, state: AnimationState) -> &AnimationInfo {
+        &self.animations[&unit_type][&state]
+    }
+
+    pub fn collider(&self, unit_type: UnitType) -> ColliderBuilder {
+        self.colliders[&unit_type].to_builder()
+    }
+
+    /// What a producer standing on the biome emits.
+    pub fn production(&self, biome: Biome) -> ProductionInfo {
+        self.productions[&biome]
+    }
+
+    /// Items taken from the builder to place the structure.
+    pub fn cost(&self, structure_type: StructureType) -> &[ItemStack] {
+        &self.costs[&structure_type]
+    }
+
+    /// Yield and harvest speed of a kind of resource node.
+    pub fn resource(&self, resource_kind: ResourceKind) -> ResourceInfo {
+        self.resources[&resource_kind]
+    }
+
+    /// Name, health, speed and look of a kind of enemy.
+    pub fn enemy(&self, enemy_type: EnemyType) -> &EnemyInfo {
+        &self.enemies[&enemy_type]
+    }
+
+    /// Picks the kind of enemy to spawn in the biome from its weighted spawn table, given a `roll`
+    /// in `0.0..1.0`.
+    pub fn enemy_spawn(&self, biome: Biome, roll: f32) -> EnemyType {
+        let spawn_table = &self.enemy_spawns[&biome];
+        let weights: Vec<u32> = spawn_table
+            .iter()
+            .map(|(_enemy_type, weight)| *weight)
+            .collect();
+        spawn_table[weighted_choice(&weights, roll) as usize].0
+    }
+
+    /// When a kind of enemy chases, attacks and flees.
+    pub fn behavior(&self, enemy_type: EnemyType) -> BehaviorInfo {
+        self.behaviors[&enemy_type]
+    }
+
+    /// Health of the builder and what dying costs them.
+    pub fn builder(&self) -> BuilderInfo {
+        self.builder
+    }
+
+    /// When enemy waves come and how big they are.
+    pub fn waves(&self) -> &WaveSchedule {
+        &self.waves
+    }
+
+    /// Range, fire rate and weapon of a tower. Panics if the structure isn't a tower.
+    pub fn tower(&self, structure_type: StructureType) -> TowerInfo {
+        self.towers[&structure_type]
+    }
+}
+
+impl FromResources for Prefabs {
+    fn from_resources(_resources: &Resources) -> Self {
+        Prefabs::load(PREFAB_DIRECTORY)
+            .unwrap_or_else(|err| panic!("Failed to load prefabs: {}", err))
+    }
+}
+
+/// Loads a prefab file with an entry for each of `required_keys`, failing with the first
+/// problem `problem` finds with an entry.
+fn load_entries<K, V>(
+    path: &Path,
+    required_keys: &[K],
+    problem: impl Fn(&K, &V) -> Option<&'static str>,
+) -> Result<HashMap<K, V>, PrefabError>
+where
+    K: DeserializeOwned + Eq + Hash + fmt::Debug,
+    V: DeserializeOwned,
+{
+    let contents =
+        fs::read_to_string(path).map_err(|err| PrefabError::Io(path.to_path_buf(), err))?;
+    let entries = parse_entries(path, &contents, required_keys)?;
+    for (key, value) in entries.iter() {
+        validate(path, format!("{:?}", key), problem(key, value))?;
+    }
+    Ok(entries)
+}
+
+/// Loads a prefab file holding a single entry rather than one per key, failing with the problem
+/// `problem` finds with it. The entry is called `key` in errors.
+fn load_file<V: DeserializeOwned>(
+    path: &Path,
+    key: &str,
+    problem: impl Fn(&V) -> Option<&'static str>,
+) -> Result<V, PrefabError> {
+    let contents =
+        fs::read_to_string(path).map_err(|err| PrefabError::Io(path.to_path_buf(), err))?;
+    let value =
+        ron::from_str(&contents).map_err(|err| PrefabError::Malformed(path.to_path_buf(), err))?;
+    validate(path, String::from(key), problem(&value))?;
+    Ok(value)
+}
+
+/// Fails with the problem found with the entry for `key`, if there is one.
+fn validate(path: &Path, key: String, problem: Option<&'static str>) -> Result<(), PrefabError> {
+    match problem {
+        Some(reason) => Err(PrefabError::InvalidEntry(path.to_path_buf(), key, reason)),
+        None => Ok(()),
+    }
+}
+
+/// For prefab files whose entries can't be wrong once they parse.
+fn no_problem<K, V>(_key: &K, _value: &V) -> Option<&'static str> {
+    Option::None
+}
+
+fn animation_problem(
+    _unit_type: &UnitType,
+    animations: &HashMap<AnimationState, AnimationInfo>,
+) -> Option<&'static str> {
+    if ALL_ANIMATION_STATES
+        .iter()
+        .any(|state| !animations.contains_key(state))
+    {
+        Some("every animation state needs an entry")
+    } else if animations
+        .values()
+        .any(|animation_info| animation_info.durations.is_empty())
+    {
+        Some("durations must not be empty")
+    } else {
+        Option::None
+    }
+}
+
+fn production_problem(_biome: &Biome, production_info: &ProductionInfo) -> Option<&'static str> {
+    if production_info.seconds_per_item <= 0. {
+        Some("seconds_per_item must be positive")
+    } else {
+        Option::None
+    }
+}
+
+fn tower_problem(_structure_type: &StructureType, tower_info: &TowerInfo) -> Option<&'static str> {
+    if tower_info.range <= 0. {
+        Some("range must be positive")
+    } else if tower_info.seconds_per_shot <= 0. {
+        Some("seconds_per_shot must be positive")
+    } else if tower_info.ammo.is_some() && tower_info.ammo_capacity == 0 {
+        Some("ammo_capacity must be positive when the tower uses ammo")
+    } else {
+        Option::None
+    }
+}
+
+// takes the vector itself to match the entries of the costs file
+#[allow(clippy::ptr_arg)]
+fn cost_problem(_structure_type: &StructureType, cost: &Vec<ItemStack>) -> Option<&'static str> {
+    if cost.iter().any(|stack| stack.count == 0) {
+        Some("item counts must be positive")
+    } else {
+        Option::None
+    }
+}
+
+fn resource_problem(
+    _resource_kind: &ResourceKind,
+    resource_info: &ResourceInfo,
+) -> Option<&'static str> {
+    if resource_info.total_yield == 0 {
+        Some("total_yield must be positive")
+    } else if resource_info.seconds_per_item <= 0. {
+        Some("seconds_per_item must be positive")
+    } else if resource_info
+        .regrowth_seconds
+        .is_some_and(|seconds| seconds <= 0.)
+    {
+        Some("regrowth_seconds must be positive")
+    } else {
+        Option::None
+    }
+}
+
+fn enemy_problem(_enemy_type: &EnemyType, enemy_info: &EnemyInfo) -> Option<&'static str> {
+    if enemy_info.max_hp <= 0 {
+        Some("max_hp must be positive")
+    } else if enemy_info.speed <= 0. {
+        Some("speed must be positive")
+    } else {
+        Option::None
+    }
+}
+
+fn enemy_spawn_problem(_biome: &Biome, spawn_table: &SpawnTable) -> Option<&'static str> {
+    if spawn_table.iter().all(|(_enemy_type, weight)| *weight == 0) {
+        Some("at least one enemy must have a positive weight")
+    } else {
+        Option::None
+    }
+}
+
+fn behavior_problem(
+    enemy_type: &EnemyType,
+    behavior_info: &BehaviorInfo,
+    animations: &HashMap<UnitType, HashMap<AnimationState, AnimationInfo>>,
+) -> Option<&'static str> {
+    if behavior_info.attack_distance > behavior_info.search_distance {
+        Some("attack_distance must not exceed search_distance")
+    } else if behavior_info.seconds_per_attack <= 0. {
+        Some("seconds_per_attack must be positive")
+    } else if behavior_info.flee_health < 0. || behavior_info.flee_health >= 1. {
+        Some("flee_health must be at least 0 and below 1")
+    } else if behavior_info.attack_frame
+        >= animations[&UnitType::Enemy(*enemy_type)][&AnimationState::Attack].length
+    {
+        Some("attack_frame must be a frame of the attack animation")
+    } else {
+        Option::None
+    }
+}
+
+fn builder_problem(builder: &BuilderInfo) -> Option<&'static str> {
+    if builder.max_hp <= 0 {
+        Some("max_hp must be positive")
+    } else if builder.death_penalty < 0. || builder.death_penalty > 1. {
+        Some("death_penalty must be between 0 and 1")
+    } else {
+        Option::None
+    }
+}
+
+fn wave_problem(waves: &WaveSchedule) -> Option<&'static str> {
+    if waves.waves.is_empty() {
+        Some("there must be at least one wave")
+    } else if waves.first_wave_seconds <= 0. || waves.seconds_between_waves <= 0. {
+        Some("the time before waves must be positive")
+    } else if waves
+        .waves
+        .iter()
+        .any(|wave| wave.enemies == 0 || wave.extra_hp < 0.)
+    {
+        Some("every wave needs enemies, with no less than their usual hp")
+    } else if waves.hp_growth_per_wave < 0. {
+        Some("hp_growth_per_wave must not be negative")
+    } else {
+        Option::None
+    }
+}
+
+fn parse_entries<K, V>(
+    path: &Path,
+    contents: &str,
+    required_keys: &[K],
+) -> Result<HashMap<K, V>, PrefabError>
+where
+    K: DeserializeOwned + Eq + Hash + fmt::Debug,
+    V: DeserializeOwned,
+{
+    let entries: HashMap<K, V> =
+        ron::from_str(contents).map_err(|err| PrefabError::Malformed(path.to_path_buf(), err))?;
+    for key in required_keys {
+        if !entries.contains_key(key) {
+            return Err(PrefabError::MissingEntry(
+                path.to_path_buf(),
+                format!("{:?}", key),
+            ));
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::Item;
+
+    #[test]
+    fn test_load_shipped_prefabs() {
+        let prefabs =
+            Prefabs::load(Path::new(env!("CARGO_MANIFEST_DIR")).join(PREFAB_DIRECTORY)).unwrap();
+
+        let magic_bow = prefabs.weapon(WeaponType::MagicBow);
+        assert_eq!(Some(ProjectileType::Arrow), magic_bow.projectile_type);
+        assert_eq!(8, magic_bow.projectile_count);
+
+        let wizard_attack = prefabs.animation(UnitType::Wizard, AnimationState::Attack);
+        assert_eq!(7, wizard_attack.length);
+        assert!(!wizard_attack.loop_around);
+
+        assert_eq!(Item::Sand, prefabs.production(Biome::Desert).item);
+
+        let arrow_tower = prefabs.tower(StructureType::ArrowTower);
+        assert_eq!(WeaponType::Crossbow, arrow_tower.weapon);
+
+        assert_eq!(
+            &[ItemStack::new(Item::Wood, 1)],
+            prefabs.cost(StructureType::Conveyor)
+        );
+        assert!(!prefabs.cost(StructureType::Wall).is_empty());
+
+        assert_eq!(Item::Wood, prefabs.resource(ResourceKind::Tree).item);
+
+        let goblin_behavior = prefabs.behavior(EnemyType::Goblin);
+        assert!(goblin_behavior.attack_distance < goblin_behavior.search_distance);
+        assert!(prefabs.enemy(EnemyType::Golem).speed < prefabs.enemy(EnemyType::Goblin).speed);
+        assert_eq!(EnemyType::Scorpion, prefabs.enemy_spawn(Biome::Desert, 0.));
+        assert_eq!(EnemyType::Goblin, prefabs.enemy_spawn(Biome::Desert, 0.99));
+
+        assert!(prefabs.builder().max_hp > 0);
+        assert!(!prefabs.waves().waves.is_empty());
+    }
+
+    #[test]
+    fn test_parse_entries_missing_key() {
+        let result =
+            parse_entries::<WeaponType, Weapon>(Path::new(WEAPONS_FILE), "{}", &ALL_WEAPON_TYPES);
+        match result {
+            Err(PrefabError::MissingEntry(_, key)) => assert_eq!("MagicBow", key),
+            _ => panic!("expected a missing entry error"),
+        }
+    }
+
+    #[test]
+    fn test_parse_entries_malformed() {
+        let result = parse_entries::<ProjectileType, Projectile>(
+            Path::new(PROJECTILES_FILE),
+            "{ Arrow: (damage: \"lots\") }",
+            &ALL_PROJECTILE_TYPES,
+        );
+        assert!(matches!(result, Err(PrefabError::Malformed(_, _))));
+    }
+
+    #[test]
+    fn test_animation_problem() {
+        let mut animations = HashMap::new();
+        animations.insert(AnimationState::Idle, AnimationInfo::new(0, vec![0.1], true));
+        animations.insert(AnimationState::Move, AnimationInfo::new(7, vec![0.1], true));
+        assert_eq!(
+            Some("every animation state needs an entry"),
+            animation_problem(&UnitType::Wizard, &animations)
+        );
+
+        animations.insert(
+            AnimationState::Attack,
+            AnimationInfo::new(14, vec![], false),
+        );
+        assert_eq!(
+            Some("durations must not be empty"),
+            animation_problem(&UnitType::Wizard, &animations)
+        );
+
+        animations.insert(
+            AnimationState::Attack,
+            AnimationInfo::new(14, vec![0.1], false),
+        );
+        assert_eq!(
+            Option::None,
+            animation_problem(&UnitType::Wizard, &animations)
+        );
+    }
+}
@@ -0,0 +1,10 @@
+use serde::Deserialize;
+
+use crate::item::Item;
+
+/// What a producer emits on a given biome, and how often.
+#[derive(Deserialize, Copy, Clone, Debug)]
+pub struct ProductionInfo {
+    pub item: Item,
+    pub seconds_per_item: f32,
+}
@@ -0,0 +1,14 @@
+use serde::Deserialize;
+
+use crate::item::Item;
+
+/// What a resource node gives when harvested, and how much of it.
+#[derive(Deserialize, Copy, Clone, Debug)]
+pub struct ResourceInfo {
+    pub item: Item,
+    // items harvested before the node is depleted
+    pub total_yield: u32,
+    pub seconds_per_item: f32,
+    // depleted nodes never come back without one
+    pub regrowth_seconds: Option<f32>,
+}
@@ -0,0 +1,16 @@
+use serde::Deserialize;
+
+use crate::item::Item;
+use crate::weapon::WeaponType;
+
+/// How far and how often a tower shoots, and what it shoots with.
+#[derive(Deserialize, Copy, Clone, Debug)]
+pub struct TowerInfo {
+    // in pixels from the tower's center
+    pub range: f32,
+    pub seconds_per_shot: f32,
+    pub weapon: WeaponType,
+    // item taken from conveyors for every shot; towers without one never run out
+    pub ammo: Option<Item>,
+    pub ammo_capacity: u32,
+}
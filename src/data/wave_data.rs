@@ -0,0 +1,92 @@
+use serde::Deserialize;
+
+use crate::enemy::EnemyType;
+
+/// One wave of enemies sent at the builder.
+#[derive(Deserialize, PartialEq, Copy, Clone, Debug)]
+pub struct WaveInfo {
+    pub enemies: u32,
+    // kind of every enemy in the wave, picked from the spawn table of the biome the wave arrives
+    // from when absent
+    #[serde(default)]
+    pub enemy_type: Option<EnemyType>,
+    // share of hit points each enemy gets on top of the usual for its type
+    #[serde(default)]
+    pub extra_hp: f32,
+}
+
+impl WaveInfo {
+    /// Hit points of an enemy of the wave whose type usually has `max_hp`.
+    pub fn hp(&self, max_hp: i32) -> i32 {
+        (max_hp as f32 * (1. + self.extra_hp)) as i32
+    }
+}
+
+/// When waves come and what is in them. Waves past the listed ones repeat the last, growing a
+/// little every time.
+#[derive(Deserialize, Clone, Debug)]
+pub struct WaveSchedule {
+    pub first_wave_seconds: f32,
+    pub seconds_between_waves: f32,
+    pub waves: Vec<WaveInfo>,
+    // added to the last listed wave for every wave past it
+    pub extra_enemies_per_wave: u32,
+    // share of hit points added to the last listed wave for every wave past it
+    pub hp_growth_per_wave: f32,
+}
+
+impl WaveSchedule {
+    /// The wave with the given number, counting from 0.
+    pub fn wave(&self, number: usize) -> WaveInfo {
+        let last_index = self.waves.len() - 1;
+        let wave = self.waves[number.min(last_index)];
+        let extra_waves = number.saturating_sub(last_index);
+        WaveInfo {
+            enemies: wave.enemies + self.extra_enemies_per_wave * extra_waves as u32,
+            extra_hp: wave.extra_hp + self.hp_growth_per_wave * extra_waves as f32,
+            ..wave
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_waves_grow_past_the_schedule() {
+        let schedule = WaveSchedule {
+            first_wave_seconds: 10.,
+            seconds_between_waves: 10.,
+            waves: vec![
+                WaveInfo {
+                    enemies: 2,
+                    enemy_type: Option::None,
+                    extra_hp: 0.,
+                },
+                WaveInfo {
+                    enemies: 4,
+                    enemy_type: Some(EnemyType::Golem),
+                    extra_hp: 0.25,
+                },
+            ],
+            extra_enemies_per_wave: 3,
+            hp_growth_per_wave: 0.5,
+        };
+
+        let first_wave = schedule.wave(0);
+        assert_eq!(2, first_wave.enemies);
+        assert_eq!(None, first_wave.enemy_type);
+        assert_eq!(80, first_wave.hp(80));
+
+        let second_wave = schedule.wave(1);
+        assert_eq!(4, second_wave.enemies);
+        assert_eq!(100, second_wave.hp(80));
+
+        // two waves past the last listed one
+        let fourth_wave = schedule.wave(3);
+        assert_eq!(10, fourth_wave.enemies);
+        assert_eq!(Some(EnemyType::Golem), fourth_wave.enemy_type);
+        assert_eq!(180, fourth_wave.hp(80));
+    }
+}
@@ -0,0 +1,164 @@
+use bevy::prelude::*;
+use bevy_rapier3d::physics::RigidBodyHandleComponent;
+use bevy_rapier3d::rapier::dynamics::RigidBodySet;
+
+use crate::builder::{Builder, BuilderMode};
+use crate::construction::{builder_tile, cursor_tile, in_reach, Structure};
+use crate::cursor::CursorState;
+use crate::data::Prefabs;
+use crate::input_bindings::InputBindings;
+use crate::inventory::{Inventory, ItemStack};
+use crate::item::Item;
+use crate::logistics::Conveyor;
+use crate::production::Producer;
+use crate::structure_grid::StructureGrid;
+
+// time the remove action has to be held on a structure to take it down
+const DECONSTRUCTION_SECONDS: f32 = 0.4;
+
+// scale of the structure's sprite right before it is removed
+const DECONSTRUCTED_SCALE: f32 = 0.4;
+
+/// The structure being taken down and how long the remove action has been held on it.
+#[derive(Default)]
+pub struct DeconstructionState {
+    target: Option<(Entity, Timer)>,
+}
+
+/// Takes down the structure under the cursor while the remove action is held on it, freeing its
+/// tiles and refunding its cost and the items it held to the builder. Sweeping the cursor while
+/// holding the action takes down every structure on the way.
+#[allow(clippy::too_many_arguments)]
+pub fn deconstruct_object(
+    mut commands: Commands,
+    time: Res<Time>,
+    input_bindings: Res<InputBindings>,
+    prefabs: Res<Prefabs>,
+    cursor_state: Res<CursorState>,
+    rigid_body_set: Res<RigidBodySet>,
+    mut structure_grid: ResMut<StructureGrid>,
+    mut deconstruction_state: ResMut<DeconstructionState>,
+    mut builder_query: Query<(&Builder, &RigidBodyHandleComponent, &mut Inventory)>,
+    mut structure_query: Query<(&Structure, &mut Transform)>,
+    mut conveyor_query: Query<&mut Conveyor>,
+    mut producer_query: Query<&mut Producer>,
+) {
+    let builder_tile = builder_query
+        .iter_mut()
+        .next()
+        .filter(|(builder, _builder_body_handle, _inventory)| {
+            builder.mode == BuilderMode::Construct
+        })
+        .map(|(_builder, builder_body_handle, _inventory)| {
+            builder_tile(&rigid_body_set, builder_body_handle)
+        });
+
+    let mut target_entity = Option::None;
+    if let (Some(builder_tile), Some(map_tile)) = (builder_tile, cursor_tile(&cursor_state)) {
+        if input_bindings.action_pressed("remove") {
+            target_entity = structure_grid.get(map_tile).filter(|structure_entity| {
+                structure_query
+                    .get_component::<Structure>(*structure_entity)
+                    .is_ok_and(|structure| in_reach(builder_tile, &structure.footprint()))
+            });
+        }
+    }
+
+    // moving off a structure before it's done leaves it standing
+    let current_entity = deconstruction_state
+        .target
+        .as_ref()
+        .map(|(structure_entity, _timer)| *structure_entity);
+    if current_entity != target_entity {
+        if let Some(current_entity) = current_entity {
+            if let Ok(mut transform) =
+                structure_query.get_component_mut::<Transform>(current_entity)
+            {
+                transform.scale = Vec3::one();
+            }
+        }
+        deconstruction_state.target = target_entity.map(|structure_entity| {
+            (
+                structure_entity,
+                Timer::from_seconds(DECONSTRUCTION_SECONDS, false),
+            )
+        });
+    }
+
+    if let Some((structure_entity, timer)) = deconstruction_state.target.as_mut() {
+        let structure_entity = *structure_entity;
+        timer.tick(time.delta_seconds);
+        let progress = (timer.elapsed / timer.duration).min(1.);
+        let finished = timer.finished;
+
+        if let Ok(mut transform) = structure_query.get_component_mut::<Transform>(structure_entity)
+        {
+            // shrink the sprite as the structure comes apart
+            let scale = 1. - (1. - DECONSTRUCTED_SCALE) * progress;
+            transform.scale = Vec3::new(scale, scale, 1.);
+        }
+
+        if finished {
+            let mut items = match conveyor_query.get_component_mut::<Conveyor>(structure_entity) {
+                Ok(mut conveyor) => conveyor.clear(&mut commands),
+                Err(_) => Vec::new(),
+            };
+            if let Ok(mut producer) = producer_query.get_component_mut::<Producer>(structure_entity)
+            {
+                items.extend(producer.take_output());
+            }
+            if let Ok(structure) = structure_query.get_component::<Structure>(structure_entity) {
+                println!("Deconstructed {:?}", structure.structure_type);
+                if let Some((_builder, _builder_body_handle, mut inventory)) =
+                    builder_query.iter_mut().next()
+                {
+                    let cost = prefabs.cost(structure.structure_type);
+                    for lost in refund(&mut inventory, cost, &items) {
+                        println!("No room for {} {:?}, it was lost", lost.count, lost.item);
+                    }
+                }
+            }
+            structure_grid.remove(structure_entity);
+            commands.despawn(structure_entity);
+            deconstruction_state.target = Option::None;
+        }
+    }
+}
+
+/// Gives the builder back what a structure cost and the items it was holding, as far as there is
+/// room. Returns what didn't fit.
+fn refund(inventory: &mut Inventory, cost: &[ItemStack], held_items: &[Item]) -> Vec<ItemStack> {
+    let held_stacks = held_items.iter().map(|item| ItemStack::new(*item, 1));
+    cost.iter()
+        .copied()
+        .chain(held_stacks)
+        .filter_map(|stack| {
+            let lost = inventory.add(stack.item, stack.count);
+            if lost > 0 {
+                Some(ItemStack::new(stack.item, lost))
+            } else {
+                Option::None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refund_cost_and_held_items() {
+        let mut inventory = Inventory::new(2);
+        let cost = [
+            ItemStack::new(Item::Wood, 2),
+            ItemStack::new(Item::Stone, 1),
+        ];
+
+        let lost = refund(&mut inventory, &cost, &[Item::Wood, Item::Sand]);
+        assert_eq!(3, inventory.count(Item::Wood));
+        assert_eq!(1, inventory.count(Item::Stone));
+        // both slots are taken, so the sand doesn't fit
+        assert_eq!(vec![ItemStack::new(Item::Sand, 1)], lost);
+    }
+}
@@ -0,0 +1,116 @@
+use std::fs;
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy::window::WindowMode;
+use serde::Deserialize;
+
+pub const DISPLAY_CONFIG_PATH: &str = "config/display.ron";
+
+/// Marks the camera looking at the world, as opposed to the one drawing the UI.
+pub struct WorldCamera;
+
+#[derive(Deserialize, PartialEq, Eq, Copy, Clone, Debug)]
+pub enum DisplayMode {
+    Windowed,
+    Borderless,
+    Fullscreen,
+}
+
+/// Window and camera settings read from `config/display.ron`. Missing fields use the defaults.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct DisplayConfig {
+    pub title: String,
+    pub dimensions: Option<(u32, u32)>,
+    pub vsync: bool,
+    pub mode: DisplayMode,
+    pub camera_scale: f32,
+    // multiplier for UI font and element sizes
+    pub ui_scale: f32,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        DisplayConfig {
+            title: String::from("Automagical"),
+            dimensions: Option::None,
+            vsync: true,
+            mode: DisplayMode::Windowed,
+            camera_scale: 0.3,
+            ui_scale: 1.,
+        }
+    }
+}
+
+impl DisplayConfig {
+    /// Reads the display config, falling back to defaults if the file is missing or invalid.
+    pub fn load<P: AsRef<Path>>(path: P) -> DisplayConfig {
+        let path = path.as_ref();
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(err) => {
+                eprintln!(
+                    "Warning: could not read {}, using default display settings: {}",
+                    path.display(),
+                    err
+                );
+                return DisplayConfig::default();
+            }
+        };
+        DisplayConfig::from_ron(&source).unwrap_or_else(|err| {
+            eprintln!(
+                "Warning: invalid {}, using default display settings: {}",
+                path.display(),
+                err
+            );
+            DisplayConfig::default()
+        })
+    }
+
+    pub fn from_ron(source: &str) -> Result<DisplayConfig, ron::Error> {
+        ron::from_str(source)
+    }
+
+    pub fn window_descriptor(&self) -> WindowDescriptor {
+        let mut window_descriptor = WindowDescriptor {
+            title: self.title.clone(),
+            vsync: self.vsync,
+            mode: match self.mode {
+                DisplayMode::Windowed => WindowMode::Windowed,
+                DisplayMode::Borderless => WindowMode::BorderlessFullscreen,
+                DisplayMode::Fullscreen => WindowMode::Fullscreen { use_size: false },
+            },
+            ..Default::default()
+        };
+        if let Some((width, height)) = self.dimensions {
+            window_descriptor.width = width;
+            window_descriptor.height = height;
+        }
+        window_descriptor
+    }
+}
+
+/// Spawns the camera that draws every piece of UI, from the hotbar to the wave countdown.
+pub fn spawn_ui_camera(mut commands: Commands) {
+    commands.spawn(UiCameraComponents::default());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_config_uses_defaults() {
+        let display_config =
+            DisplayConfig::from_ron("(title: \"test\", dimensions: Some((800, 600)))").unwrap();
+        assert_eq!("test", display_config.title);
+        assert_eq!(Some((800, 600)), display_config.dimensions);
+        assert_eq!(DisplayMode::Windowed, display_config.mode);
+        assert_eq!(0.3, display_config.camera_scale);
+
+        let window_descriptor = display_config.window_descriptor();
+        assert_eq!(800, window_descriptor.width);
+        assert_eq!(600, window_descriptor.height);
+    }
+}
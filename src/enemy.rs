@@ -1,89 +1,257 @@
-use crate::animation::{Animated, AnimationState, CardinalDirection};
+use crate::animation::{Animated, AnimationBundle, AnimationState, CardinalDirection, UnitType};
+use crate::asset_loader::AtlasHandles;
 use crate::builder::Builder;
+use crate::data::{AssetType, Prefabs};
+use crate::enemy_brain::{BrainState, EnemyBrain};
+use crate::enemy_registry::{EnemyId, EnemyRecord};
+use crate::health::Health;
+use crate::pathfinding::Pathfinding;
+use crate::world_map::{position_to_tile, tile_to_position, TileCoordinate};
 use bevy::prelude::*;
 use bevy_rapier3d::physics::RigidBodyHandleComponent;
-use bevy_rapier3d::rapier::dynamics::{RigidBody, RigidBodySet};
+use bevy_rapier3d::rapier::dynamics::{RigidBody, RigidBodyBuilder, RigidBodySet};
 use bevy_rapier3d::rapier::na;
 use bevy_rapier3d::rapier::na::{Isometry3, Point3, Vector3};
+use serde::{Deserialize, Serialize};
 
-const ENEMY_SPEED: f32 = 30.;
+// distances are squared to simplify operations, the search and attack distances are the defaults
+// of the enemy behavior prefabs
+pub const ENEMY_SEARCH_DISTANCE: f32 = 22500.;
+pub const ENEMY_ATTACK_DISTANCE: f32 = 625.;
+// close enough to a destination to stop walking
+const ARRIVED_DISTANCE: f32 = 16.;
 
-// distances are squared to simplify operations
-const ENEMY_SEARCH_DISTANCE: f32 = 22500.;
-const ENEMY_ATTACK_DISTANCE: f32 = 625.;
+/// Kinds of enemies, each with its own stats, behavior, sprite sheet, animations and collider in
+/// the prefabs.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Copy, Clone, Hash, Debug)]
+pub enum EnemyType {
+    Goblin,
+    Scorpion,
+    Golem,
+}
+
+pub const ALL_ENEMY_TYPES: [EnemyType; 3] =
+    [EnemyType::Goblin, EnemyType::Scorpion, EnemyType::Golem];
 
+/// An enemy's identity; its hit points are in its `Health`.
 pub struct Enemy {
-    pub hp: i32,
+    pub id: EnemyId,
+    pub enemy_type: EnemyType,
     pub name: String,
 }
 
 impl Enemy {
-    pub fn new(hp: i32, name: String) -> Self {
-        Enemy { hp, name }
+    pub fn new(id: EnemyId, enemy_type: EnemyType, name: String) -> Self {
+        Enemy {
+            id,
+            enemy_type,
+            name,
+        }
     }
+}
 
-    pub fn generic_enemy() -> Self {
-        Enemy::new(80, String::from("generic enemy"))
-    }
+/// Spawns the entity for a registered enemy, restoring its health and position.
+pub fn spawn_enemy(
+    commands: &mut Commands,
+    atlas_handles: &AtlasHandles,
+    prefabs: &Prefabs,
+    record: &EnemyRecord,
+) -> Entity {
+    let enemy_type = record.enemy_type;
+    let enemy_info = prefabs.enemy(enemy_type);
+    let unit_type = UnitType::Enemy(enemy_type);
+    let enemy_atlas_handle = Handle::weak(
+        atlas_handles
+            .get_asset(AssetType::Enemy(enemy_type))
+            .unwrap(),
+    );
+    // TODO: this should use UNIT_Z instead of 0.
+    let (x, y) = record.position;
+    let enemy = Enemy::new(record.id, enemy_type, enemy_info.name.clone());
+    // raiders of later waves can be tougher than the usual enemy of their type
+    let mut health = Health::new(enemy_info.max_hp.max(record.hp));
+    health.hp = record.hp;
 
-    pub fn take_damage(&mut self, damage: i32) {
-        self.hp -= damage;
-    }
+    let enemy_entity = commands
+        .spawn(SpriteSheetComponents {
+            texture_atlas: enemy_atlas_handle,
+            sprite: TextureAtlasSprite::new(7),
+            transform: Transform::from_translation(Vec3::new(x, y, 0.)),
+            ..Default::default()
+        })
+        .with_bundle(AnimationBundle::new(unit_type, prefabs))
+        .with(enemy)
+        .with(health)
+        .with(if record.raider {
+            EnemyBrain::raider(record.home)
+        } else {
+            EnemyBrain::new(record.home)
+        })
+        .current_entity()
+        .unwrap();
+
+    let enemy_body = RigidBodyBuilder::new_dynamic()
+        .translation(x, y, 0.)
+        .lock_rotations()
+        .lock_translations()
+        .mass(1000., false);
+    let enemy_collider = prefabs
+        .collider(unit_type)
+        .user_data(enemy_entity.to_bits() as u128);
+    commands.insert(enemy_entity, (enemy_body, enemy_collider));
+    enemy_entity
 }
 
+/// Acts on each enemy's brain state at the speed of its type: chasing walks the shared flow field
+/// toward the builder, wandering and returning home walk straight to their tile, fleeing runs
+/// directly away and attacking stands still and strikes whenever it is ready.
 pub fn move_enemies(
+    time: Res<Time>,
+    prefabs: Res<Prefabs>,
+    pathfinding: Res<Pathfinding>,
     mut rigid_body_set: ResMut<RigidBodySet>,
     builder_query: Query<(&Builder, &RigidBodyHandleComponent)>,
-    mut enemy_query: Query<(&mut Animated, &Enemy, &RigidBodyHandleComponent)>,
+    mut enemy_query: Query<(
+        &Enemy,
+        &mut Timer,
+        &mut Animated,
+        &mut EnemyBrain,
+        &RigidBodyHandleComponent,
+    )>,
 ) {
-    let mut builder_positions: Vec<Isometry3<f32>> = Vec::new();
-    for (_builder, rigid_body_handle) in builder_query.iter() {
-        builder_positions.push(
-            rigid_body_set
-                .get(rigid_body_handle.handle())
-                .unwrap()
-                .position()
-                .clone(),
-        );
-    }
+    let builder_position = builder_query
+        .iter()
+        .next()
+        .and_then(|(_builder, builder_body_handle)| {
+            rigid_body_set.get(builder_body_handle.handle())
+        })
+        .map(|builder_body| *builder_body.position());
 
-    for (mut animated, _enemy, rigid_body_handle) in enemy_query.iter_mut() {
+    for (enemy, mut enemy_timer, mut animated, mut brain, rigid_body_handle) in
+        enemy_query.iter_mut()
+    {
+        let behavior = prefabs.behavior(enemy.enemy_type);
+        let speed = prefabs.enemy(enemy.enemy_type).speed;
         let rigid_body = rigid_body_set.get_mut(rigid_body_handle.handle()).unwrap();
-        // if enemy is still, look for hero if close enough nearby and walk toward them
-        if animated.state == AnimationState::Idle {
-            let mut reset_enemy = true;
-            for builder_position in &builder_positions {
-                let distance = distance_to_position(rigid_body, &builder_position);
-                // println!("Enemy distance {}", distance);
-                if distance < ENEMY_SEARCH_DISTANCE {
-                    animated.state = AnimationState::Move;
-                    animated.animation_index = 0;
-
-                    let movement_direction =
-                        direction_to_position(rigid_body, &builder_position) * ENEMY_SPEED;
-
-                    if movement_direction.x < 0. {
-                        animated.facing = CardinalDirection::West;
-                    } else {
-                        animated.facing = CardinalDirection::East;
-                    }
-                    rigid_body.set_linvel(movement_direction, false);
-
-                    if distance < ENEMY_ATTACK_DISTANCE {
-                        animated.state = AnimationState::Attack;
-                        animated.animation_index = 0;
-                    }
-                    reset_enemy = false;
-                }
-            }
+        let destination = match brain.state {
+            BrainState::Idle | BrainState::Attack => Option::None,
+            BrainState::Wander => brain
+                .wander_destination
+                .map(|tile| tile_position(tile, rigid_body)),
+            BrainState::Chase => next_waypoint(&pathfinding, rigid_body).or(builder_position),
+            BrainState::ReturnHome => Some(tile_position(brain.home, rigid_body)),
+            BrainState::Flee => builder_position.map(|builder_position| {
+                // a point as far on the other side of the enemy
+                let mut away = *rigid_body.position();
+                away.translation.vector +=
+                    rigid_body.position().translation.vector - builder_position.translation.vector;
+                away
+            }),
+        };
+
+        let movement_direction = destination
+            .filter(|destination| distance_to_position(rigid_body, destination) > ARRIVED_DISTANCE)
+            .map(|destination| direction_to_position(rigid_body, &destination) * speed)
+            .unwrap_or_else(Vector3::zeros);
+        rigid_body.set_linvel(movement_direction, false);
+
+        let facing_target = if brain.state == BrainState::Attack {
+            builder_position.map(|builder_position| {
+                builder_position.translation.vector - rigid_body.position().translation.vector
+            })
+        } else {
+            Some(movement_direction)
+        };
+        if let Some(facing_target) = facing_target.filter(|target| target.x != 0.) {
+            animated.facing = if facing_target.x < 0. {
+                CardinalDirection::West
+            } else {
+                CardinalDirection::East
+            };
+        }
 
-            if reset_enemy {
-                rigid_body.set_linvel(Vector3::zeros(), false);
+        brain.attack_cooldown = (brain.attack_cooldown - time.delta_seconds).max(0.);
+        if brain.state == BrainState::Attack {
+            if brain.attack_cooldown == 0. {
+                brain.attack_cooldown = behavior.seconds_per_attack;
+                brain.strike_pending = true;
+                animated.state = AnimationState::Attack;
+                animated.animation_index = 0;
+                enemy_timer.reset();
+                enemy_timer.finished = true;
             }
+        } else if animated.state == AnimationState::Idle && movement_direction != Vector3::zeros() {
+            animated.state = AnimationState::Move;
+            animated.animation_index = 0;
         }
     }
 }
 
+/// Lands the blow of every enemy whose attack animation reaches its striking frame, hurting the
+/// builder if they are still in reach.
+pub fn strike_builder(
+    prefabs: Res<Prefabs>,
+    rigid_body_set: Res<RigidBodySet>,
+    mut builder_query: Query<(&Builder, &RigidBodyHandleComponent, &mut Health)>,
+    mut enemy_query: Query<(
+        &Enemy,
+        &Animated,
+        &mut EnemyBrain,
+        &RigidBodyHandleComponent,
+    )>,
+) {
+    let (builder, builder_body_handle, mut builder_health) = match builder_query.iter_mut().next() {
+        Some(builder) => builder,
+        None => return,
+    };
+    let builder_position = *rigid_body_set
+        .get(builder_body_handle.handle())
+        .unwrap()
+        .position();
+    let invulnerable_seconds = prefabs.builder().invulnerable_seconds;
+
+    for (enemy, animated, mut brain, enemy_body_handle) in enemy_query.iter_mut() {
+        let behavior = prefabs.behavior(enemy.enemy_type);
+        if !brain.strike_pending
+            || animated.state != AnimationState::Attack
+            || animated.animation_index != behavior.attack_frame
+        {
+            continue;
+        }
+        brain.strike_pending = false;
+
+        let enemy_body = rigid_body_set.get(enemy_body_handle.handle()).unwrap();
+        let in_reach = distance_to_position(enemy_body, &builder_position)
+            <= behavior.attack_distance * behavior.attack_distance;
+        if in_reach && builder_health.hit(behavior.attack_damage, invulnerable_seconds) {
+            println!(
+                "{} hit {} for {}, hp {}",
+                enemy.name, builder.name, behavior.attack_damage, builder_health.hp
+            );
+        }
+    }
+}
+
+/// Center of the tile at the enemy's height.
+fn tile_position(tile: TileCoordinate, rigid_body: &RigidBody) -> Isometry3<f32> {
+    let position = tile_to_position(tile).translation;
+    Isometry3::translation(
+        position.x(),
+        position.y(),
+        rigid_body.position().translation.z,
+    )
+}
+
+/// Center of the next tile on the enemy's way along the flow field. There is none once the enemy
+/// shares the builder's tile or if the field doesn't lead it there.
+fn next_waypoint(pathfinding: &Pathfinding, rigid_body: &RigidBody) -> Option<Isometry3<f32>> {
+    let translation = rigid_body.position().translation;
+    let waypoint = pathfinding
+        .flow_field()?
+        .waypoint(position_to_tile(translation.x, translation.y))?;
+    Some(tile_position(waypoint, rigid_body))
+}
+
 /// Returns the squared distance between the builder and enemy.
 ///
 /// sqrt operation (for correctness) is skipped to avoid an extra operation
@@ -0,0 +1,322 @@
+use bevy::prelude::*;
+use bevy_rapier3d::physics::RigidBodyHandleComponent;
+use bevy_rapier3d::rapier::dynamics::RigidBodySet;
+
+use crate::builder::Builder;
+use crate::data::{BehaviorInfo, Prefabs};
+use crate::enemy::Enemy;
+use crate::global_constants::TILE_LENGTH;
+use crate::health::Health;
+use crate::world_map::{tile_to_position, TileCoordinate};
+
+// in pixels, how close to home counts as back home
+const HOME_DISTANCE: f32 = TILE_LENGTH as f32 / 2.;
+
+/// What an enemy is up to. Kept apart from the animation state, which only says what it looks like
+/// it's doing.
+#[derive(PartialEq, Eq, Copy, Clone, Hash, Debug)]
+pub enum BrainState {
+    Idle,
+    Wander,
+    Chase,
+    Attack,
+    ReturnHome,
+    Flee,
+}
+
+/// What an enemy knows about its surroundings when deciding what to do next.
+#[derive(Copy, Clone, Debug)]
+pub struct Perception {
+    // in pixels, to the builder if there is one
+    pub target_distance: Option<f32>,
+    // in pixels, to the center of the enemy's home tile
+    pub home_distance: f32,
+    // share of its health left
+    pub health: f32,
+    // seconds since the enemy entered its current state
+    pub time_in_state: f32,
+}
+
+impl BrainState {
+    /// The state to be in given what the enemy perceives and how it behaves.
+    pub fn next(self, perception: &Perception, behavior: &BehaviorInfo) -> BrainState {
+        let target_within = |distance: f32| {
+            perception
+                .target_distance
+                .is_some_and(|target_distance| target_distance <= distance)
+        };
+        let sees_target = target_within(behavior.search_distance);
+        let wounded = perception.health <= behavior.flee_health;
+
+        match self {
+            BrainState::Idle | BrainState::Wander if sees_target && wounded => BrainState::Flee,
+            BrainState::Idle | BrainState::Wander if sees_target => BrainState::Chase,
+            BrainState::Idle if perception.time_in_state >= behavior.idle_seconds => {
+                BrainState::Wander
+            }
+            BrainState::Wander if perception.time_in_state >= behavior.wander_seconds => {
+                BrainState::Idle
+            }
+            BrainState::Chase | BrainState::Attack if wounded => BrainState::Flee,
+            // the builder escaped or led the enemy too far from home
+            BrainState::Chase | BrainState::Attack
+                if !sees_target || perception.home_distance > behavior.leash_distance =>
+            {
+                BrainState::ReturnHome
+            }
+            BrainState::Chase | BrainState::Attack if target_within(behavior.attack_distance) => {
+                BrainState::Attack
+            }
+            BrainState::Attack => BrainState::Chase,
+            // leashed enemies ignore the builder until they're back home
+            BrainState::ReturnHome if perception.home_distance <= HOME_DISTANCE => BrainState::Idle,
+            BrainState::Flee if !sees_target => BrainState::ReturnHome,
+            state => state,
+        }
+    }
+}
+
+/// An enemy's current behavior and the home it wanders around and returns to.
+pub struct EnemyBrain {
+    pub state: BrainState,
+    pub home: TileCoordinate,
+    // tile walked to while wandering, picked on entering the state
+    pub wander_destination: Option<TileCoordinate>,
+    // sent by a wave, so it always knows where the builder is and never gives up the chase
+    pub raider: bool,
+    // seconds until the enemy can strike again
+    pub attack_cooldown: f32,
+    // whether the current attack animation has yet to land its blow
+    pub strike_pending: bool,
+    // seconds since entering the current state
+    time_in_state: f32,
+}
+
+impl EnemyBrain {
+    pub fn new(home: TileCoordinate) -> Self {
+        EnemyBrain {
+            state: BrainState::Idle,
+            home,
+            wander_destination: Option::None,
+            raider: false,
+            attack_cooldown: 0.,
+            strike_pending: false,
+            time_in_state: 0.,
+        }
+    }
+
+    pub fn raider(home: TileCoordinate) -> Self {
+        EnemyBrain {
+            raider: true,
+            ..EnemyBrain::new(home)
+        }
+    }
+
+    /// How the enemy behaves: raiders see the builder from anywhere, and are never leashed nor
+    /// scared off.
+    pub fn behavior(&self, behavior: BehaviorInfo) -> BehaviorInfo {
+        if self.raider {
+            BehaviorInfo {
+                search_distance: f32::INFINITY,
+                leash_distance: f32::INFINITY,
+                flee_health: 0.,
+                ..behavior
+            }
+        } else {
+            behavior
+        }
+    }
+
+    /// Moves to the next state, returning whether it changed.
+    pub fn think(&mut self, perception: &Perception, behavior: &BehaviorInfo) -> bool {
+        let next_state = self.state.next(perception, behavior);
+        if next_state == self.state {
+            return false;
+        }
+        self.state = next_state;
+        self.time_in_state = 0.;
+        self.wander_destination = if next_state == BrainState::Wander {
+            Some(random_tile_around(self.home, behavior.wander_radius))
+        } else {
+            Option::None
+        };
+        true
+    }
+}
+
+fn random_tile_around(center: TileCoordinate, radius: i32) -> TileCoordinate {
+    let offset = |roll: f32| (roll * (2 * radius + 1) as f32) as i32 - radius;
+    TileCoordinate(
+        center.0 + offset(rand::random()),
+        center.1 + offset(rand::random()),
+    )
+}
+
+/// Lets every enemy take in its surroundings and switch states. Acting on the state is up to
+/// `move_enemies`.
+pub fn update_enemy_brains(
+    time: Res<Time>,
+    prefabs: Res<Prefabs>,
+    rigid_body_set: Res<RigidBodySet>,
+    builder_query: Query<(&Builder, &RigidBodyHandleComponent)>,
+    mut enemy_query: Query<(&Enemy, &Health, &mut EnemyBrain, &RigidBodyHandleComponent)>,
+) {
+    let builder_position = builder_query
+        .iter()
+        .next()
+        .and_then(|(_builder, builder_body_handle)| {
+            rigid_body_set.get(builder_body_handle.handle())
+        })
+        .map(|builder_body| builder_body.position().translation.vector.xy());
+
+    for (enemy, health, mut brain, enemy_body_handle) in enemy_query.iter_mut() {
+        let enemy_position = match rigid_body_set.get(enemy_body_handle.handle()) {
+            Some(enemy_body) => enemy_body.position().translation.vector.xy(),
+            None => continue,
+        };
+        let home = tile_to_position(brain.home).translation;
+        brain.time_in_state += time.delta_seconds;
+
+        let perception = Perception {
+            target_distance: builder_position
+                .map(|builder_position| (builder_position - enemy_position).norm()),
+            home_distance: (enemy_position.x - home.x()).hypot(enemy_position.y - home.y()),
+            health: health.fraction(),
+            time_in_state: brain.time_in_state,
+        };
+        let behavior = brain.behavior(prefabs.behavior(enemy.enemy_type));
+        brain.think(&perception, &behavior);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn perceive(target_distance: Option<f32>, home_distance: f32) -> Perception {
+        Perception {
+            target_distance,
+            home_distance,
+            health: 1.,
+            time_in_state: 0.,
+        }
+    }
+
+    #[test]
+    fn test_chase_and_attack() {
+        let behavior = BehaviorInfo::default();
+        let far = behavior.search_distance + 1.;
+        let near = (behavior.search_distance + behavior.attack_distance) / 2.;
+        let close = behavior.attack_distance / 2.;
+
+        assert_eq!(
+            BrainState::Idle,
+            BrainState::Idle.next(&perceive(Some(far), 0.), &behavior)
+        );
+        assert_eq!(
+            BrainState::Chase,
+            BrainState::Idle.next(&perceive(Some(near), 0.), &behavior)
+        );
+        assert_eq!(
+            BrainState::Attack,
+            BrainState::Chase.next(&perceive(Some(close), 0.), &behavior)
+        );
+        // backing off out of reach restarts the chase
+        assert_eq!(
+            BrainState::Chase,
+            BrainState::Attack.next(&perceive(Some(near), 0.), &behavior)
+        );
+    }
+
+    #[test]
+    fn test_leash_back_home() {
+        let behavior = BehaviorInfo::default();
+        let near = behavior.attack_distance + 1.;
+
+        // the builder got away
+        assert_eq!(
+            BrainState::ReturnHome,
+            BrainState::Chase.next(&perceive(None, 50.), &behavior)
+        );
+        // the builder is still close, but the enemy strayed too far
+        let strayed = perceive(Some(near), behavior.leash_distance + 1.);
+        assert_eq!(
+            BrainState::ReturnHome,
+            BrainState::Chase.next(&strayed, &behavior)
+        );
+        // on the way back the builder is ignored
+        assert_eq!(
+            BrainState::ReturnHome,
+            BrainState::ReturnHome.next(&strayed, &behavior)
+        );
+        assert_eq!(
+            BrainState::Idle,
+            BrainState::ReturnHome.next(&perceive(Some(near), 0.), &behavior)
+        );
+    }
+
+    #[test]
+    fn test_flee_when_wounded() {
+        let behavior = BehaviorInfo {
+            flee_health: 0.25,
+            ..BehaviorInfo::default()
+        };
+        let mut wounded = perceive(Some(behavior.attack_distance), 0.);
+        wounded.health = 0.2;
+
+        assert_eq!(
+            BrainState::Flee,
+            BrainState::Attack.next(&wounded, &behavior)
+        );
+        assert_eq!(BrainState::Flee, BrainState::Flee.next(&wounded, &behavior));
+        wounded.target_distance = Option::None;
+        assert_eq!(
+            BrainState::ReturnHome,
+            BrainState::Flee.next(&wounded, &behavior)
+        );
+    }
+
+    #[test]
+    fn test_raiders_never_give_up() {
+        let behavior = BehaviorInfo::default();
+        let brain = EnemyBrain::raider(TileCoordinate(0, 0));
+        let far_from_everything = perceive(
+            Some(behavior.search_distance * 10.),
+            behavior.leash_distance * 10.,
+        );
+
+        assert_eq!(
+            BrainState::Chase,
+            BrainState::Idle.next(&far_from_everything, &brain.behavior(behavior))
+        );
+        assert_eq!(
+            BrainState::Chase,
+            BrainState::Chase.next(&far_from_everything, &brain.behavior(behavior))
+        );
+        // while ordinary enemies lose interest
+        assert_eq!(
+            BrainState::ReturnHome,
+            BrainState::Chase.next(&far_from_everything, &behavior)
+        );
+    }
+
+    #[test]
+    fn test_idle_and_wander() {
+        let behavior = BehaviorInfo::default();
+        let mut brain = EnemyBrain::new(TileCoordinate(4, -2));
+        let mut perception = perceive(None, 0.);
+
+        assert!(!brain.think(&perception, &behavior));
+        perception.time_in_state = behavior.idle_seconds;
+        assert!(brain.think(&perception, &behavior));
+        assert_eq!(BrainState::Wander, brain.state);
+        let destination = brain.wander_destination.unwrap();
+        assert!((destination.0 - 4).abs() <= behavior.wander_radius);
+        assert!((destination.1 + 2).abs() <= behavior.wander_radius);
+
+        perception.time_in_state = behavior.wander_seconds;
+        assert!(brain.think(&perception, &behavior));
+        assert_eq!(BrainState::Idle, brain.state);
+        assert_eq!(None, brain.wander_destination);
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_rapier3d::physics::RigidBodyHandleComponent;
+use bevy_rapier3d::rapier::dynamics::RigidBodySet;
+use serde::{Deserialize, Serialize};
+
+use crate::enemy::{Enemy, EnemyType};
+use crate::health::Health;
+use crate::world_map::{position_to_tile, tile_to_position, TileCoordinate, WorldMap};
+
+// in tiles beyond the rendered region, so enemies near the screen edge don't flicker in and out
+const ENEMY_DESPAWN_MARGIN: i32 = 6;
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Copy, Clone, Hash, Debug)]
+pub struct EnemyId(pub u64);
+
+/// Everything needed to restore an enemy after its entity has been despawned.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EnemyRecord {
+    pub id: EnemyId,
+    pub enemy_type: EnemyType,
+    pub hp: i32,
+    pub position: (f32, f32),
+    // tile the enemy was placed on by world generation or sent from by a wave
+    pub home: TileCoordinate,
+    // sent by a wave, so it goes after the builder wherever they are
+    pub raider: bool,
+    #[serde(skip)]
+    pub entity: Option<Entity>,
+}
+
+impl EnemyRecord {
+    pub fn tile(&self) -> TileCoordinate {
+        position_to_tile(self.position.0, self.position.1)
+    }
+}
+
+/// Every living enemy in the world, whether or not it currently has an entity.
+///
+/// Enemies far off-screen are despawned and kept here as dormant records, indexed by tile so they
+/// can be restored when their tile comes back into view.
+#[derive(Default)]
+pub struct EnemyRegistry {
+    next_id: u64,
+    records: HashMap<EnemyId, EnemyRecord>,
+    dormant_by_tile: HashMap<TileCoordinate, Vec<EnemyId>>,
+}
+
+impl EnemyRegistry {
+    /// Rebuilds a registry from saved records, with every enemy dormant.
+    pub fn restore(next_id: u64, records: Vec<EnemyRecord>) -> Self {
+        let mut enemy_registry = EnemyRegistry {
+            next_id,
+            ..Default::default()
+        };
+        for mut record in records {
+            record.entity = Option::None;
+            enemy_registry
+                .dormant_by_tile
+                .entry(record.tile())
+                .or_default()
+                .push(record.id);
+            enemy_registry.records.insert(record.id, record);
+        }
+        enemy_registry
+    }
+
+    pub fn next_id(&self) -> u64 {
+        self.next_id
+    }
+
+    pub fn records(&self) -> impl Iterator<Item = &EnemyRecord> {
+        self.records.values()
+    }
+
+    /// Adds a dormant enemy standing on its home tile.
+    pub fn register(&mut self, home: TileCoordinate, enemy_type: EnemyType, hp: i32) -> EnemyId {
+        let id = EnemyId(self.next_id);
+        self.next_id += 1;
+
+        let position = tile_to_position(home).translation;
+        self.records.insert(
+            id,
+            EnemyRecord {
+                id,
+                enemy_type,
+                hp,
+                position: (position.x(), position.y()),
+                home,
+                raider: false,
+                entity: Option::None,
+            },
+        );
+        self.dormant_by_tile.entry(home).or_default().push(id);
+        id
+    }
+
+    /// Adds a dormant enemy sent by a wave, starting out on the given tile.
+    pub fn register_raider(
+        &mut self,
+        home: TileCoordinate,
+        enemy_type: EnemyType,
+        hp: i32,
+    ) -> EnemyId {
+        let id = self.register(home, enemy_type, hp);
+        self.records.get_mut(&id).unwrap().raider = true;
+        id
+    }
+
+    /// Forgets a killed enemy for good.
+    pub fn remove(&mut self, id: EnemyId) -> Option<EnemyRecord> {
+        let record = self.records.remove(&id)?;
+        if record.entity.is_none() {
+            self.remove_dormant(id, record.tile());
+        }
+        Some(record)
+    }
+
+    pub fn get_mut(&mut self, id: EnemyId) -> Option<&mut EnemyRecord> {
+        self.records.get_mut(&id)
+    }
+
+    /// Returns the dormant enemies on the tile, which are expected to be spawned and then passed
+    /// to `set_spawned`.
+    pub fn wake_at(&mut self, tile: TileCoordinate) -> Vec<EnemyRecord> {
+        match self.dormant_by_tile.remove(&tile) {
+            Some(ids) => ids
+                .iter()
+                .filter_map(|id| self.records.get(id))
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn set_spawned(&mut self, id: EnemyId, entity: Entity) {
+        if let Some(record) = self.records.get_mut(&id) {
+            record.entity.replace(entity);
+        }
+    }
+
+    pub fn set_dormant(&mut self, id: EnemyId) {
+        if let Some(record) = self.records.get_mut(&id) {
+            if record.entity.take().is_some() {
+                self.dormant_by_tile
+                    .entry(record.tile())
+                    .or_default()
+                    .push(id);
+            }
+        }
+    }
+
+    fn remove_dormant(&mut self, id: EnemyId, tile: TileCoordinate) {
+        if let Some(ids) = self.dormant_by_tile.get_mut(&tile) {
+            ids.retain(|dormant_id| *dormant_id != id);
+            if ids.is_empty() {
+                self.dormant_by_tile.remove(&tile);
+            }
+        }
+    }
+}
+
+/// Copies live enemy state into the registry, and despawns enemies other than raiders that are far
+/// off-screen.
+pub fn update_enemy_records(
+    mut commands: Commands,
+    mut enemy_registry: ResMut<EnemyRegistry>,
+    world_map: Res<WorldMap>,
+    rigid_body_set: Res<RigidBodySet>,
+    enemy_query: Query<(Entity, &Enemy, &Health, &RigidBodyHandleComponent)>,
+) {
+    let keep_region = match world_map.visible_region() {
+        Some(visible_region) => visible_region.expanded(ENEMY_DESPAWN_MARGIN),
+        None => return,
+    };
+
+    for (entity, enemy, health, rigid_body_handle) in enemy_query.iter() {
+        let translation = rigid_body_set
+            .get(rigid_body_handle.handle())
+            .unwrap()
+            .position()
+            .translation;
+        let mut raider = false;
+        if let Some(record) = enemy_registry.get_mut(enemy.id) {
+            record.hp = health.hp;
+            record.position = (translation.x, translation.y);
+            raider = record.raider;
+        }
+
+        // raiders keep chasing the builder however far behind they fall
+        if !raider && !keep_region.contains(position_to_tile(translation.x, translation.y)) {
+            commands.despawn(entity);
+            enemy_registry.set_dormant(enemy.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wake_and_sleep() {
+        let mut enemy_registry = EnemyRegistry::default();
+        let id = enemy_registry.register(TileCoordinate(2, -3), EnemyType::Goblin, 80);
+
+        assert!(enemy_registry.wake_at(TileCoordinate(0, 0)).is_empty());
+        let woken = enemy_registry.wake_at(TileCoordinate(2, -3));
+        assert_eq!(1, woken.len());
+        assert_eq!(id, woken[0].id);
+        enemy_registry.set_spawned(id, Entity::from_bits(7));
+        assert!(enemy_registry.wake_at(TileCoordinate(2, -3)).is_empty());
+
+        // the enemy walked to another tile before being despawned
+        let moved_position = tile_to_position(TileCoordinate(5, 5)).translation;
+        enemy_registry.get_mut(id).unwrap().position = (moved_position.x(), moved_position.y());
+        enemy_registry.set_dormant(id);
+        assert!(enemy_registry.wake_at(TileCoordinate(2, -3)).is_empty());
+        assert_eq!(1, enemy_registry.wake_at(TileCoordinate(5, 5)).len());
+    }
+
+    #[test]
+    fn test_removed_enemies_stay_dead() {
+        let mut enemy_registry = EnemyRegistry::default();
+        let dormant_id = enemy_registry.register(TileCoordinate(0, 0), EnemyType::Goblin, 80);
+        let spawned_id = enemy_registry.register(TileCoordinate(0, 0), EnemyType::Goblin, 80);
+        enemy_registry.wake_at(TileCoordinate(0, 0));
+        enemy_registry.set_spawned(dormant_id, Entity::from_bits(1));
+        enemy_registry.set_spawned(spawned_id, Entity::from_bits(2));
+        enemy_registry.set_dormant(dormant_id);
+
+        assert!(enemy_registry.remove(dormant_id).is_some());
+        assert!(enemy_registry.remove(spawned_id).is_some());
+        assert!(!enemy_registry.records.contains_key(&spawned_id));
+        assert!(enemy_registry.wake_at(TileCoordinate(0, 0)).is_empty());
+    }
+}
@@ -0,0 +1,101 @@
+use bevy::prelude::*;
+
+// opacity of the sprite of anything currently shrugging off hits
+const INVULNERABLE_ALPHA: f32 = 0.5;
+
+/// Hit points of anything that can be hurt: the builder, enemies and structures.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct Health {
+    pub hp: i32,
+    pub max_hp: i32,
+    // seconds left during which hits are ignored
+    invulnerable_seconds: f32,
+}
+
+impl Health {
+    pub fn new(max_hp: i32) -> Self {
+        Health {
+            hp: max_hp,
+            max_hp,
+            invulnerable_seconds: 0.,
+        }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.hp <= 0
+    }
+
+    /// Share of the maximum hit points left.
+    pub fn fraction(&self) -> f32 {
+        self.hp.max(0) as f32 / self.max_hp as f32
+    }
+
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerable_seconds > 0.
+    }
+
+    /// Takes the damage unless hits are being ignored, and then ignores hits for
+    /// `invulnerable_seconds`. Returns whether the hit landed.
+    pub fn hit(&mut self, damage: i32, invulnerable_seconds: f32) -> bool {
+        if self.is_invulnerable() {
+            return false;
+        }
+        self.hp -= damage;
+        self.invulnerable_seconds = invulnerable_seconds;
+        true
+    }
+
+    /// Heals back to full, ignoring hits for `invulnerable_seconds`.
+    pub fn restore(&mut self, invulnerable_seconds: f32) {
+        self.hp = self.max_hp;
+        self.invulnerable_seconds = invulnerable_seconds;
+    }
+
+    pub fn tick(&mut self, delta_seconds: f32) {
+        self.invulnerable_seconds = (self.invulnerable_seconds - delta_seconds).max(0.);
+    }
+}
+
+/// Counts down invulnerability, fading the sprites of whatever can't be hurt at the moment.
+pub fn update_health(time: Res<Time>, mut query: Query<(&mut Health, &mut TextureAtlasSprite)>) {
+    for (mut health, mut sprite) in query.iter_mut() {
+        health.tick(time.delta_seconds);
+        sprite.color.set_a(if health.is_invulnerable() {
+            INVULNERABLE_ALPHA
+        } else {
+            1.
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hits_while_invulnerable_are_ignored() {
+        let mut health = Health::new(100);
+
+        assert!(health.hit(30, 1.));
+        assert_eq!(70, health.hp);
+        assert!(!health.hit(30, 1.));
+        assert_eq!(70, health.hp);
+
+        health.tick(0.6);
+        assert!(health.is_invulnerable());
+        health.tick(0.6);
+        assert!(health.hit(80, 0.));
+        assert!(health.is_dead());
+        assert_eq!(0., health.fraction());
+
+        // hits without invulnerability all land
+        let mut health = Health::new(10);
+        assert!(health.hit(4, 0.));
+        assert!(health.hit(4, 0.));
+        assert_eq!(0.2, health.fraction());
+
+        health.restore(2.);
+        assert_eq!(10, health.hp);
+        assert!(!health.hit(4, 0.));
+    }
+}
@@ -0,0 +1,217 @@
+use bevy::app::{EventReader, Events};
+use bevy::input::mouse::MouseWheel;
+use bevy::prelude::*;
+
+use crate::builder::{Builder, BuilderMode};
+use crate::construction::{PlacementState, StructureType, ALL_STRUCTURE_TYPES};
+use crate::data::Prefabs;
+use crate::display::DisplayConfig;
+use crate::input_bindings::InputBindings;
+use crate::inventory::{Inventory, ItemStack};
+
+const HOTBAR_FONT: &str = "font/square.ttf";
+const HOTBAR_FONT_SIZE: f32 = 18.;
+
+fn selected_color() -> Color {
+    Color::rgb(1., 0.9, 0.3)
+}
+
+fn unselected_color() -> Color {
+    Color::rgb(0.8, 0.8, 0.8)
+}
+
+fn unaffordable_color() -> Color {
+    Color::rgb(0.6, 0.3, 0.3)
+}
+
+fn hidden_color() -> Color {
+    Color::rgba(0., 0., 0., 0.)
+}
+
+/// Shows every placeable structure along the bottom of the screen while in construct mode, and
+/// selects among them with the number keys, the scroll wheel or `select_next`.
+pub struct HotbarPlugin;
+
+impl Plugin for HotbarPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Hotbar>()
+            .add_startup_system(spawn_hotbar.system())
+            .add_system(select_structure.system())
+            .add_system(update_hotbar.system());
+    }
+}
+
+#[derive(Default)]
+pub struct Hotbar {
+    mouse_wheel: EventReader<MouseWheel>,
+}
+
+/// Marks the text of the hotbar slot holding the structure.
+pub struct HotbarSlot(StructureType);
+
+fn spawn_hotbar(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    display_config: Res<DisplayConfig>,
+    prefabs: Res<Prefabs>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let font = asset_server.load(HOTBAR_FONT);
+    let font_size = HOTBAR_FONT_SIZE * display_config.ui_scale;
+
+    commands
+        .spawn(NodeComponents {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.),
+                    bottom: Val::Px(10.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            material: materials.add(hidden_color().into()),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            for (index, structure_type) in ALL_STRUCTURE_TYPES.iter().enumerate() {
+                parent
+                    .spawn(TextComponents {
+                        style: Style {
+                            margin: Rect::all(Val::Px(6.)),
+                            ..Default::default()
+                        },
+                        text: Text {
+                            value: slot_label(
+                                index,
+                                *structure_type,
+                                prefabs.cost(*structure_type),
+                            ),
+                            font: font.clone(),
+                            style: TextStyle {
+                                font_size,
+                                color: hidden_color(),
+                            },
+                        },
+                        ..Default::default()
+                    })
+                    .with(HotbarSlot(*structure_type));
+            }
+        });
+}
+
+fn select_structure(
+    input_bindings: Res<InputBindings>,
+    mouse_wheel_events: Res<Events<MouseWheel>>,
+    mut hotbar: ResMut<Hotbar>,
+    mut placement_state: ResMut<PlacementState>,
+    builder_query: Query<&Builder>,
+) {
+    // the wheel is read every frame so scrolling in combat doesn't pile up for later
+    let mut steps = 0;
+    for mouse_wheel in hotbar.mouse_wheel.iter(&mouse_wheel_events) {
+        if mouse_wheel.y < 0. {
+            steps += 1;
+        } else if mouse_wheel.y > 0. {
+            steps -= 1;
+        }
+    }
+    if input_bindings.action_just_pressed("select_next") {
+        steps += 1;
+    }
+
+    let constructing = builder_query
+        .iter()
+        .next()
+        .is_some_and(|builder| builder.mode == BuilderMode::Construct);
+    if !constructing {
+        return;
+    }
+
+    let mut selected = cycle(placement_state.selected, steps);
+    for (index, structure_type) in ALL_STRUCTURE_TYPES.iter().enumerate() {
+        if input_bindings.action_just_pressed(&format!("hotbar_{}", index + 1)) {
+            selected = *structure_type;
+        }
+    }
+    if selected != placement_state.selected {
+        placement_state.selected = selected;
+        println!("Selected {:?}", selected);
+    }
+}
+
+/// Highlights the selected structure and dims the ones the builder can't afford. The hotbar is
+/// hidden outside of construct mode.
+fn update_hotbar(
+    prefabs: Res<Prefabs>,
+    placement_state: Res<PlacementState>,
+    builder_query: Query<(&Builder, &Inventory)>,
+    mut slot_query: Query<(&HotbarSlot, &mut Text)>,
+) {
+    let inventory = builder_query
+        .iter()
+        .next()
+        .filter(|(builder, _inventory)| builder.mode == BuilderMode::Construct)
+        .map(|(_builder, inventory)| inventory);
+
+    for (slot, mut text) in slot_query.iter_mut() {
+        let structure_type = slot.0;
+        text.style.color = match inventory {
+            None => hidden_color(),
+            Some(_inventory) if structure_type == placement_state.selected => selected_color(),
+            Some(inventory) if inventory.missing(prefabs.cost(structure_type)).is_some() => {
+                unaffordable_color()
+            }
+            Some(_inventory) => unselected_color(),
+        };
+    }
+}
+
+/// Moves the selection by `steps` slots, wrapping around either end.
+fn cycle(selected: StructureType, steps: i32) -> StructureType {
+    let slot_count = ALL_STRUCTURE_TYPES.len() as i32;
+    let index = ALL_STRUCTURE_TYPES
+        .iter()
+        .position(|structure_type| *structure_type == selected)
+        .unwrap() as i32;
+    ALL_STRUCTURE_TYPES[(index + steps).rem_euclid(slot_count) as usize]
+}
+
+fn slot_label(index: usize, structure_type: StructureType, cost: &[ItemStack]) -> String {
+    let cost = cost
+        .iter()
+        .map(|stack| format!("{} {:?}", stack.count, stack.item))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("{} {:?} ({})", index + 1, structure_type, cost)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::Item;
+
+    #[test]
+    fn test_cycle() {
+        let last = ALL_STRUCTURE_TYPES[ALL_STRUCTURE_TYPES.len() - 1];
+        assert_eq!(ALL_STRUCTURE_TYPES[1], cycle(ALL_STRUCTURE_TYPES[0], 1));
+        assert_eq!(last, cycle(ALL_STRUCTURE_TYPES[0], -1));
+        assert_eq!(ALL_STRUCTURE_TYPES[0], cycle(last, 1));
+        assert_eq!(
+            ALL_STRUCTURE_TYPES[0],
+            cycle(ALL_STRUCTURE_TYPES[0], ALL_STRUCTURE_TYPES.len() as i32)
+        );
+    }
+
+    #[test]
+    fn test_slot_label() {
+        let cost = [
+            ItemStack::new(Item::Wood, 10),
+            ItemStack::new(Item::Stone, 5),
+        ];
+        assert_eq!(
+            "2 Producer (10 Wood, 5 Stone)",
+            slot_label(1, StructureType::Producer, &cost)
+        );
+    }
+}
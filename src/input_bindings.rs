@@ -0,0 +1,298 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+pub const BINDINGS_PATH: &str = "config/bindings.ron";
+
+pub struct InputBindingsPlugin;
+
+impl Plugin for InputBindingsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<InputBindings>()
+            .add_system_to_stage(stage::PRE_UPDATE, update_input_bindings.system());
+    }
+}
+
+#[derive(Deserialize, Copy, Clone, PartialEq, Debug)]
+pub enum InputButton {
+    Key(#[serde(with = "KeyCodeDef")] KeyCode),
+    Mouse(#[serde(with = "MouseButtonDef")] MouseButton),
+}
+
+impl InputButton {
+    fn pressed(
+        &self,
+        keyboard_input: &Input<KeyCode>,
+        mouse_button_input: &Input<MouseButton>,
+    ) -> bool {
+        match *self {
+            InputButton::Key(key_code) => keyboard_input.pressed(key_code),
+            InputButton::Mouse(mouse_button) => mouse_button_input.pressed(mouse_button),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub enum InputAxis {
+    /// An axis driven by two buttons: `pos` reads as 1, `neg` as -1 and both at once as 0.
+    Emulated { pos: InputButton, neg: InputButton },
+}
+
+/// Outer list holds alternative bindings, inner list holds the buttons of a chord.
+pub type ActionBinding = Vec<Vec<InputButton>>;
+
+#[derive(Deserialize, Default)]
+struct Bindings {
+    axes: HashMap<String, InputAxis>,
+    actions: HashMap<String, ActionBinding>,
+}
+
+/// Named axes and actions read from `config/bindings.ron`, resolved once per frame against the
+/// raw keyboard and mouse state.
+pub struct InputBindings {
+    bindings: Bindings,
+    axis_values: HashMap<String, f32>,
+    pressed_actions: HashSet<String>,
+    previously_pressed_actions: HashSet<String>,
+}
+
+impl InputBindings {
+    pub fn from_ron(source: &str) -> Result<InputBindings, ron::Error> {
+        let bindings: Bindings = ron::from_str(source)?;
+        Ok(InputBindings {
+            bindings,
+            axis_values: HashMap::new(),
+            pressed_actions: HashSet::new(),
+            previously_pressed_actions: HashSet::new(),
+        })
+    }
+
+    /// Returns the axis value in `-1.0..=1.0`, or 0 for an unbound axis.
+    pub fn axis_value(&self, axis: &str) -> f32 {
+        self.axis_values.get(axis).cloned().unwrap_or(0.)
+    }
+
+    pub fn action_pressed(&self, action: &str) -> bool {
+        self.pressed_actions.contains(action)
+    }
+
+    pub fn action_just_pressed(&self, action: &str) -> bool {
+        self.pressed_actions.contains(action) && !self.previously_pressed_actions.contains(action)
+    }
+
+    pub fn action_just_released(&self, action: &str) -> bool {
+        !self.pressed_actions.contains(action) && self.previously_pressed_actions.contains(action)
+    }
+
+    fn update(&mut self, keyboard_input: &Input<KeyCode>, mouse_button_input: &Input<MouseButton>) {
+        for (name, axis) in self.bindings.axes.iter() {
+            let value = match axis {
+                InputAxis::Emulated { pos, neg } => {
+                    let mut value = 0.;
+                    if pos.pressed(keyboard_input, mouse_button_input) {
+                        value += 1.;
+                    }
+                    if neg.pressed(keyboard_input, mouse_button_input) {
+                        value -= 1.;
+                    }
+                    value
+                }
+            };
+            self.axis_values.insert(name.clone(), value);
+        }
+
+        self.previously_pressed_actions = std::mem::take(&mut self.pressed_actions);
+        for (name, chords) in self.bindings.actions.iter() {
+            let pressed = chords.iter().any(|chord| {
+                !chord.is_empty()
+                    && chord
+                        .iter()
+                        .all(|button| button.pressed(keyboard_input, mouse_button_input))
+            });
+            if pressed {
+                self.pressed_actions.insert(name.clone());
+            }
+        }
+    }
+}
+
+impl FromResources for InputBindings {
+    fn from_resources(_resources: &Resources) -> Self {
+        let source = fs::read_to_string(BINDINGS_PATH)
+            .unwrap_or_else(|err| panic!("Failed to read {}: {}", BINDINGS_PATH, err));
+        InputBindings::from_ron(&source)
+            .unwrap_or_else(|err| panic!("Failed to parse {}: {}", BINDINGS_PATH, err))
+    }
+}
+
+pub fn update_input_bindings(
+    mut input_bindings: ResMut<InputBindings>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_button_input: Res<Input<MouseButton>>,
+) {
+    input_bindings.update(&keyboard_input, &mouse_button_input);
+}
+
+#[derive(Deserialize)]
+#[serde(remote = "MouseButton")]
+#[allow(dead_code)]
+enum MouseButtonDef {
+    Left,
+    Right,
+    Middle,
+    Other(u8),
+}
+
+/// Key names accepted in the bindings file, matching bevy's `KeyCode` variants.
+#[derive(Deserialize)]
+#[serde(remote = "KeyCode")]
+#[allow(dead_code)]
+enum KeyCodeDef {
+    Key1,
+    Key2,
+    Key3,
+    Key4,
+    Key5,
+    Key6,
+    Key7,
+    Key8,
+    Key9,
+    Key0,
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    Escape,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    Insert,
+    Home,
+    Delete,
+    End,
+    PageDown,
+    PageUp,
+    Left,
+    Up,
+    Right,
+    Down,
+    Back,
+    Return,
+    Space,
+    Numpad0,
+    Numpad1,
+    Numpad2,
+    Numpad3,
+    Numpad4,
+    Numpad5,
+    Numpad6,
+    Numpad7,
+    Numpad8,
+    Numpad9,
+    Apostrophe,
+    Backslash,
+    Comma,
+    Equals,
+    Grave,
+    LAlt,
+    LBracket,
+    LControl,
+    LShift,
+    Minus,
+    Period,
+    RAlt,
+    RBracket,
+    RControl,
+    RShift,
+    Semicolon,
+    Slash,
+    Tab,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_BINDINGS: &str = r#"(
+        axes: {
+            "horizontal": Emulated(pos: Key(D), neg: Key(A)),
+        },
+        actions: {
+            "place": [[Mouse(Left)], [Key(Space)]],
+            "save": [[Key(LControl), Key(S)]],
+        },
+    )"#;
+
+    #[test]
+    fn test_axis_value() {
+        let mut input_bindings = InputBindings::from_ron(TEST_BINDINGS).unwrap();
+        let mut keyboard_input = Input::<KeyCode>::default();
+        let mouse_button_input = Input::<MouseButton>::default();
+
+        keyboard_input.press(KeyCode::A);
+        input_bindings.update(&keyboard_input, &mouse_button_input);
+        assert_eq!(-1., input_bindings.axis_value("horizontal"));
+
+        keyboard_input.press(KeyCode::D);
+        input_bindings.update(&keyboard_input, &mouse_button_input);
+        assert_eq!(0., input_bindings.axis_value("horizontal"));
+
+        assert_eq!(0., input_bindings.axis_value("vertical"));
+    }
+
+    #[test]
+    fn test_action_alternatives_and_chords() {
+        let mut input_bindings = InputBindings::from_ron(TEST_BINDINGS).unwrap();
+        let mut keyboard_input = Input::<KeyCode>::default();
+        let mouse_button_input = Input::<MouseButton>::default();
+
+        keyboard_input.press(KeyCode::Space);
+        keyboard_input.press(KeyCode::S);
+        input_bindings.update(&keyboard_input, &mouse_button_input);
+        assert!(input_bindings.action_pressed("place"));
+        assert!(input_bindings.action_just_pressed("place"));
+        assert!(!input_bindings.action_pressed("save"));
+
+        keyboard_input.press(KeyCode::LControl);
+        input_bindings.update(&keyboard_input, &mouse_button_input);
+        assert!(input_bindings.action_pressed("save"));
+        assert!(!input_bindings.action_just_pressed("place"));
+
+        keyboard_input.release(KeyCode::Space);
+        input_bindings.update(&keyboard_input, &mouse_button_input);
+        assert!(input_bindings.action_just_released("place"));
+    }
+}
@@ -0,0 +1,215 @@
+use serde::{Deserialize, Serialize};
+
+use crate::item::Item;
+
+/// A number of items of the same kind, held in one inventory slot.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Copy, Clone, Debug)]
+pub struct ItemStack {
+    pub item: Item,
+    pub count: u32,
+}
+
+impl ItemStack {
+    pub fn new(item: Item, count: u32) -> Self {
+        ItemStack { item, count }
+    }
+}
+
+/// Items kept in a fixed number of slots, each holding a stack of up to the item's stack limit.
+///
+/// Anything that stores items carries one: the builder, and eventually chests and buildings.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Inventory {
+    slots: Vec<Option<ItemStack>>,
+}
+
+impl Inventory {
+    pub fn new(slot_count: usize) -> Self {
+        Inventory {
+            slots: vec![Option::None; slot_count],
+        }
+    }
+
+    /// Occupied slots, in slot order.
+    pub fn stacks(&self) -> impl Iterator<Item = &ItemStack> {
+        self.slots.iter().filter_map(|slot| slot.as_ref())
+    }
+
+    /// Total of the item across all stacks.
+    pub fn count(&self, item: Item) -> u32 {
+        self.slots
+            .iter()
+            .filter_map(|slot| slot.as_ref())
+            .filter(|stack| stack.item == item)
+            .map(|stack| stack.count)
+            .sum()
+    }
+
+    /// How many more of the item fit, counting the room left on its stacks and in empty slots.
+    pub fn room_for(&self, item: Item) -> u32 {
+        let stack_limit = item.stack_limit();
+        self.slots
+            .iter()
+            .map(|slot| match slot {
+                Some(stack) if stack.item == item => stack_limit - stack.count,
+                Some(_other_stack) => 0,
+                None => stack_limit,
+            })
+            .sum()
+    }
+
+    /// Adds as many of the items as fit, topping up existing stacks before starting new ones.
+    /// Returns how many were left over.
+    pub fn add(&mut self, item: Item, count: u32) -> u32 {
+        let stack_limit = item.stack_limit();
+        let mut remaining = count;
+
+        for stack in self.slots.iter_mut().filter_map(|slot| slot.as_mut()) {
+            if remaining == 0 {
+                break;
+            }
+            if stack.item == item {
+                let added = remaining.min(stack_limit - stack.count);
+                stack.count += added;
+                remaining -= added;
+            }
+        }
+
+        for slot in self.slots.iter_mut().filter(|slot| slot.is_none()) {
+            if remaining == 0 {
+                break;
+            }
+            let added = remaining.min(stack_limit);
+            slot.replace(ItemStack::new(item, added));
+            remaining -= added;
+        }
+        remaining
+    }
+
+    /// Removes up to `count` of the item, emptying the last stacks first. Returns how many were
+    /// removed.
+    pub fn remove(&mut self, item: Item, count: u32) -> u32 {
+        let mut remaining = count;
+        for slot in self.slots.iter_mut().rev() {
+            if remaining == 0 {
+                break;
+            }
+            if let Some(stack) = slot {
+                if stack.item == item {
+                    let removed = remaining.min(stack.count);
+                    stack.count -= removed;
+                    remaining -= removed;
+                    if stack.count == 0 {
+                        *slot = Option::None;
+                    }
+                }
+            }
+        }
+        count - remaining
+    }
+
+    /// The first of `stacks` not fully held, with how many of it are missing.
+    pub fn missing(&self, stacks: &[ItemStack]) -> Option<ItemStack> {
+        stacks.iter().find_map(|stack| {
+            let held = self.count(stack.item);
+            if held < stack.count {
+                Some(ItemStack::new(stack.item, stack.count - held))
+            } else {
+                Option::None
+            }
+        })
+    }
+
+    /// Removes every one of `stacks` if all of them are held, otherwise leaves the inventory as it
+    /// was. Returns whether they were removed.
+    pub fn remove_all(&mut self, stacks: &[ItemStack]) -> bool {
+        if self.missing(stacks).is_some() {
+            return false;
+        }
+        for stack in stacks {
+            self.remove(stack.item, stack.count);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_fills_stacks_then_slots() {
+        let mut inventory = Inventory::new(3);
+        let stack_limit = Item::Wood.stack_limit();
+
+        assert_eq!(0, inventory.add(Item::Wood, stack_limit - 2));
+        assert_eq!(0, inventory.add(Item::Stone, 1));
+        // tops up the first stack, then starts a new one in the last free slot
+        assert_eq!(0, inventory.add(Item::Wood, 5));
+        assert_eq!(
+            vec![
+                ItemStack::new(Item::Wood, stack_limit),
+                ItemStack::new(Item::Stone, 1),
+                ItemStack::new(Item::Wood, 3),
+            ],
+            inventory.stacks().cloned().collect::<Vec<_>>()
+        );
+
+        // only the rest of the last stack fits
+        assert_eq!(3, inventory.add(Item::Wood, stack_limit));
+        assert_eq!(2 * stack_limit, inventory.count(Item::Wood));
+        assert_eq!(0, inventory.room_for(Item::Sand));
+        assert_eq!(1, inventory.add(Item::Sand, 1));
+    }
+
+    #[test]
+    fn test_remove_across_stacks() {
+        let mut inventory = Inventory::new(3);
+        let stack_limit = Item::Wood.stack_limit();
+        inventory.add(Item::Wood, stack_limit + 4);
+
+        assert_eq!(6, inventory.remove(Item::Wood, 6));
+        assert_eq!(stack_limit - 2, inventory.count(Item::Wood));
+        assert_eq!(1, inventory.stacks().count());
+
+        // asking for more than is held takes what there is
+        assert_eq!(stack_limit - 2, inventory.remove(Item::Wood, stack_limit));
+        assert_eq!(0, inventory.remove(Item::Stone, 1));
+        assert_eq!(0, inventory.stacks().count());
+    }
+
+    #[test]
+    fn test_remove_all_or_nothing() {
+        let mut inventory = Inventory::new(3);
+        inventory.add(Item::Wood, 10);
+        inventory.add(Item::Stone, 2);
+        let cost = [
+            ItemStack::new(Item::Wood, 4),
+            ItemStack::new(Item::Stone, 5),
+        ];
+
+        assert_eq!(
+            Some(ItemStack::new(Item::Stone, 3)),
+            inventory.missing(&cost)
+        );
+        assert!(!inventory.remove_all(&cost));
+        assert_eq!(10, inventory.count(Item::Wood));
+
+        inventory.add(Item::Stone, 3);
+        assert_eq!(None, inventory.missing(&cost));
+        assert!(inventory.remove_all(&cost));
+        assert_eq!(6, inventory.count(Item::Wood));
+        assert_eq!(0, inventory.count(Item::Stone));
+    }
+
+    #[test]
+    fn test_serialization_round_trip() {
+        let mut inventory = Inventory::new(4);
+        inventory.add(Item::Wood, 12);
+        inventory.add(Item::Arrow, 3);
+
+        let source = ron::ser::to_string(&inventory).unwrap();
+        let loaded: Inventory = ron::from_str(&source).unwrap();
+        assert_eq!(inventory, loaded);
+    }
+}
@@ -0,0 +1,39 @@
+use bevy::prelude::Color;
+use serde::{Deserialize, Serialize};
+
+use crate::data::AssetType;
+
+/// A resource that can ride conveyors.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Copy, Clone, Hash, Debug)]
+pub enum Item {
+    Wood,
+    Sand,
+    Stone,
+    Arrow,
+}
+
+impl Item {
+    pub fn asset_type(&self) -> AssetType {
+        match self {
+            Item::Wood | Item::Sand | Item::Stone => AssetType::Wood,
+            Item::Arrow => AssetType::Arrow,
+        }
+    }
+
+    /// Most of the item a single inventory slot holds.
+    pub fn stack_limit(&self) -> u32 {
+        match self {
+            Item::Wood | Item::Sand | Item::Stone => 50,
+            Item::Arrow => 100,
+        }
+    }
+
+    // sand and stone reuse the wood sprite, tinted, until they have their own
+    pub fn tint(&self) -> Color {
+        match self {
+            Item::Wood | Item::Arrow => Color::WHITE,
+            Item::Sand => Color::rgb(1., 0.9, 0.5),
+            Item::Stone => Color::rgb(0.6, 0.6, 0.65),
+        }
+    }
+}
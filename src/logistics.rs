@@ -0,0 +1,289 @@
+use bevy::prelude::*;
+
+use crate::animation::CardinalDirection;
+use crate::asset_loader::AtlasHandles;
+use crate::construction::Structure;
+use crate::global_constants::TILE_LENGTH;
+use crate::item::Item;
+use crate::structure_grid::StructureGrid;
+use crate::world_map::tile_to_position;
+
+// number of items a single conveyor tile can hold
+pub const CONVEYOR_SLOTS: usize = 4;
+
+// time for an item to move one slot
+const CONVEYOR_TICK_SECONDS: f32 = 0.1;
+
+const ITEM_Z: f32 = 3.;
+
+pub struct LogisticsPlugin;
+
+impl Plugin for LogisticsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<LogisticsTimer>()
+            .add_system(move_items.system())
+            .add_system(render_belt_items.system());
+    }
+}
+
+pub struct LogisticsTimer(Timer);
+
+impl Default for LogisticsTimer {
+    fn default() -> Self {
+        LogisticsTimer(Timer::from_seconds(CONVEYOR_TICK_SECONDS, true))
+    }
+}
+
+/// Marks the sprite entity of an item riding a conveyor.
+pub struct ItemSprite;
+
+#[derive(Debug)]
+pub struct BeltItem {
+    pub item: Item,
+    // spawned by render_belt_items once the item is on a belt
+    sprite: Option<Entity>,
+}
+
+impl BeltItem {
+    pub fn new(item: Item) -> Self {
+        BeltItem {
+            item,
+            sprite: Option::None,
+        }
+    }
+}
+
+/// A belt moving items across its tile towards `direction`, then onto the next tile.
+pub struct Conveyor {
+    pub direction: CardinalDirection,
+    // slot 0 is at the edge where items enter, the last slot at the edge where they leave
+    slots: [Option<BeltItem>; CONVEYOR_SLOTS],
+}
+
+impl Conveyor {
+    pub fn new(direction: CardinalDirection) -> Self {
+        Conveyor {
+            direction,
+            slots: Default::default(),
+        }
+    }
+
+    /// Moves every item one slot towards the exit, unless the slot ahead is taken.
+    pub fn advance(&mut self) {
+        for slot in (0..CONVEYOR_SLOTS - 1).rev() {
+            if self.slots[slot + 1].is_none() {
+                self.slots[slot + 1] = self.slots[slot].take();
+            }
+        }
+    }
+
+    /// Whether an item coming from a belt facing `direction` can be put on the entry slot.
+    ///
+    /// Belts facing each other don't exchange items, which would otherwise bounce back and forth.
+    pub fn accepts_from(&self, direction: CardinalDirection) -> bool {
+        self.slots[0].is_none() && direction != self.direction.opposite()
+    }
+
+    /// Puts the item on the entry slot, handing it back if that slot is taken.
+    pub fn accept(&mut self, belt_item: BeltItem) -> Result<(), BeltItem> {
+        if self.slots[0].is_some() {
+            return Err(belt_item);
+        }
+        self.slots[0].replace(belt_item);
+        Ok(())
+    }
+
+    pub fn has_output(&self) -> bool {
+        self.slots[CONVEYOR_SLOTS - 1].is_some()
+    }
+
+    /// Removes the item waiting on the exit slot.
+    pub fn take_output(&mut self) -> Option<BeltItem> {
+        self.slots[CONVEYOR_SLOTS - 1].take()
+    }
+
+    /// The item waiting on the exit slot, if any.
+    pub fn output(&self) -> Option<Item> {
+        self.slots[CONVEYOR_SLOTS - 1]
+            .as_ref()
+            .map(|belt_item| belt_item.item)
+    }
+
+    /// Removes the item waiting on the exit slot for a building that uses it up, despawning its
+    /// sprite.
+    pub fn consume_output(&mut self, commands: &mut Commands) -> Option<Item> {
+        let belt_item = self.take_output()?;
+        if let Some(sprite_entity) = belt_item.sprite {
+            commands.despawn(sprite_entity);
+        }
+        Some(belt_item.item)
+    }
+
+    /// Takes every item off the belt, despawning their sprites.
+    pub fn clear(&mut self, commands: &mut Commands) -> Vec<Item> {
+        let mut items = Vec::new();
+        for belt_item in self.slots.iter_mut().filter_map(|slot| slot.take()) {
+            if let Some(sprite_entity) = belt_item.sprite {
+                commands.despawn(sprite_entity);
+            }
+            items.push(belt_item.item);
+        }
+        items
+    }
+
+    /// Offset of the slot's center from the center of the conveyor's tile.
+    fn slot_offset(&self, slot: usize) -> Vec2 {
+        let progress = (slot as f32 + 0.5) / CONVEYOR_SLOTS as f32 - 0.5;
+        self.direction.to_vector() * progress * TILE_LENGTH as f32
+    }
+}
+
+/// Advances items along every belt on each logistics tick, then hands the items waiting at belt
+/// exits to the conveyor on the next tile. Items stay put while the next tile can't take them,
+/// which backs up the belts behind.
+pub fn move_items(
+    time: Res<Time>,
+    mut logistics_timer: ResMut<LogisticsTimer>,
+    structure_grid: Res<StructureGrid>,
+    mut conveyor_query: Query<(Entity, &Structure, &mut Conveyor)>,
+) {
+    logistics_timer.0.tick(time.delta_seconds);
+    if !logistics_timer.0.finished {
+        return;
+    }
+
+    for (_conveyor_entity, _structure, mut conveyor) in conveyor_query.iter_mut() {
+        conveyor.advance();
+    }
+
+    let mut handoffs = Vec::new();
+    for (conveyor_entity, structure, conveyor) in conveyor_query.iter_mut() {
+        if conveyor.has_output() {
+            let next_tile = structure.tile.neighbour(conveyor.direction);
+            if let Some(next_entity) = structure_grid.get(next_tile) {
+                handoffs.push((conveyor_entity, next_entity, conveyor.direction));
+            }
+        }
+    }
+
+    for (conveyor_entity, next_entity, direction) in handoffs {
+        let next_accepts = conveyor_query
+            .get_component::<Conveyor>(next_entity)
+            .map(|next_conveyor| next_conveyor.accepts_from(direction))
+            .unwrap_or(false);
+        if next_accepts {
+            let belt_item = conveyor_query
+                .get_component_mut::<Conveyor>(conveyor_entity)
+                .unwrap()
+                .take_output()
+                .unwrap();
+            let mut next_conveyor = conveyor_query
+                .get_component_mut::<Conveyor>(next_entity)
+                .unwrap();
+            next_conveyor
+                .accept(belt_item)
+                .expect("the conveyor was checked to have room");
+        }
+    }
+}
+
+/// Keeps a small sprite on top of every item riding a belt.
+pub fn render_belt_items(
+    mut commands: Commands,
+    atlas_handles: Res<AtlasHandles>,
+    mut conveyor_query: Query<(&Structure, &mut Conveyor)>,
+    mut item_sprite_query: Query<(&ItemSprite, &mut Transform)>,
+) {
+    for (structure, mut conveyor) in conveyor_query.iter_mut() {
+        let tile_position = tile_to_position(structure.tile).translation;
+        for slot in 0..CONVEYOR_SLOTS {
+            let slot_offset = conveyor.slot_offset(slot);
+            let translation = Vec3::new(
+                tile_position.x() + slot_offset.x(),
+                tile_position.y() + slot_offset.y(),
+                ITEM_Z,
+            );
+
+            if let Some(belt_item) = conveyor.slots[slot].as_mut() {
+                match belt_item.sprite {
+                    Some(sprite_entity) => {
+                        if let Ok(mut transform) =
+                            item_sprite_query.get_component_mut::<Transform>(sprite_entity)
+                        {
+                            transform.translation = translation;
+                        }
+                    }
+                    None => {
+                        if let Some(item_atlas_id) =
+                            atlas_handles.get_asset(belt_item.item.asset_type())
+                        {
+                            let mut sprite = TextureAtlasSprite::new(0);
+                            sprite.color = belt_item.item.tint();
+                            belt_item.sprite = commands
+                                .spawn(SpriteSheetComponents {
+                                    texture_atlas: Handle::weak(item_atlas_id),
+                                    sprite,
+                                    transform: Transform::from_translation(translation),
+                                    ..Default::default()
+                                })
+                                .with(ItemSprite)
+                                .current_entity();
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn occupied_slots(conveyor: &Conveyor) -> Vec<usize> {
+        (0..CONVEYOR_SLOTS)
+            .filter(|slot| conveyor.slots[*slot].is_some())
+            .collect()
+    }
+
+    #[test]
+    fn test_advance() {
+        let mut conveyor = Conveyor::new(CardinalDirection::East);
+        conveyor.accept(BeltItem::new(Item::Wood)).unwrap();
+        conveyor.advance();
+        conveyor.accept(BeltItem::new(Item::Wood)).unwrap();
+        assert_eq!(vec![0, 1], occupied_slots(&conveyor));
+
+        conveyor.advance();
+        assert_eq!(vec![1, 2], occupied_slots(&conveyor));
+        conveyor.advance();
+        conveyor.advance();
+        // the leading item waits at the exit and the next one queues behind it
+        assert!(conveyor.has_output());
+        assert_eq!(vec![2, 3], occupied_slots(&conveyor));
+
+        assert!(conveyor.take_output().is_some());
+        conveyor.advance();
+        assert_eq!(vec![3], occupied_slots(&conveyor));
+    }
+
+    #[test]
+    fn test_back_pressure() {
+        let mut conveyor = Conveyor::new(CardinalDirection::North);
+        for _ in 0..CONVEYOR_SLOTS {
+            conveyor.accept(BeltItem::new(Item::Wood)).unwrap();
+            conveyor.advance();
+        }
+        assert_eq!(vec![0, 1, 2, 3], occupied_slots(&conveyor));
+        assert!(!conveyor.accepts_from(CardinalDirection::North));
+        assert!(conveyor.accept(BeltItem::new(Item::Wood)).is_err());
+    }
+
+    #[test]
+    fn test_facing_belts_do_not_exchange() {
+        let conveyor = Conveyor::new(CardinalDirection::West);
+        assert!(conveyor.accepts_from(CardinalDirection::West));
+        assert!(conveyor.accepts_from(CardinalDirection::North));
+        assert!(!conveyor.accepts_from(CardinalDirection::East));
+    }
+}
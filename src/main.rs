@@ -7,10 +7,31 @@ mod controls;
 mod cursor;
 mod damage;
 mod data;
+mod deconstruction;
+mod display;
 mod enemy;
+mod enemy_brain;
+mod enemy_registry;
 mod global_constants;
+mod health;
+mod hotbar;
+mod input_bindings;
+mod inventory;
+mod item;
+mod logistics;
+mod pathfinding;
+mod placement_preview;
+mod production;
 mod projectile;
+mod resource_node;
+mod save_game;
+mod save_migration;
+mod structure_grid;
+mod terrain_renderer;
+mod tower;
+mod waves;
 mod weapon;
+mod world_generator;
 mod world_map;
 mod world_renderer;
 
@@ -18,19 +39,44 @@ use bevy::prelude::*;
 use bevy_rapier3d::physics::RapierPhysicsPlugin;
 
 fn main() {
+    let display_config = display::DisplayConfig::load(display::DISPLAY_CONFIG_PATH);
+
     App::build()
-        .init_resource::<world_map::WorldMap>()
+        .add_resource(display_config.window_descriptor())
+        .add_resource(display_config)
+        .add_resource(world_map::WorldMap::new())
+        .init_resource::<structure_grid::StructureGrid>()
+        .init_resource::<construction::PlacementState>()
+        .init_resource::<placement_preview::PlacementPreview>()
+        .init_resource::<deconstruction::DeconstructionState>()
+        .init_resource::<pathfinding::Pathfinding>()
+        .init_resource::<data::Prefabs>()
         .add_plugins(DefaultPlugins)
         .add_plugin(RapierPhysicsPlugin)
+        .add_startup_system(display::spawn_ui_camera.system())
+        .add_plugin(input_bindings::InputBindingsPlugin)
         .add_plugin(asset_loader::AssetLoaderPlugin)
         .add_plugin(world_renderer::MapGeneratorPlugin)
+        .add_plugin(logistics::LogisticsPlugin)
+        .add_plugin(save_game::SaveGamePlugin)
+        .add_plugin(hotbar::HotbarPlugin)
+        .add_plugin(waves::WavePlugin)
         .add_system(animation::animate.system())
         .add_system(builder::produce_projectiles.system())
         .add_system(projectile::expire_projectiles.system())
         .add_system(controls::control_builder.system())
         .add_system(cursor::update_cursor_position.system())
         .add_system(construction::place_object.system())
+        .add_system(placement_preview::update_placement_preview.system())
+        .add_system(deconstruction::deconstruct_object.system())
+        .add_system(production::produce_items.system())
+        .add_system(tower::operate_towers.system())
         .add_system(damage::examine_collisions.system())
+        .add_system(pathfinding::update_flow_field.system())
+        .add_system(enemy_brain::update_enemy_brains.system())
         .add_system(enemy::move_enemies.system())
+        .add_system(enemy::strike_builder.system())
+        .add_system(health::update_health.system())
+        .add_system(builder::respawn_builder.system())
         .run();
 }
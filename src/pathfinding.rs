@@ -0,0 +1,169 @@
+use std::collections::{HashMap, VecDeque};
+
+use bevy::prelude::*;
+use bevy_rapier3d::physics::RigidBodyHandleComponent;
+use bevy_rapier3d::rapier::dynamics::RigidBodySet;
+
+use crate::builder::Builder;
+use crate::construction::builder_tile;
+use crate::structure_grid::StructureGrid;
+use crate::world_map::{TileCoordinate, TileRegion, WorldMap, ALL_DIRECTIONS};
+
+// in number of tiles around the target, comfortably more than enemies search for the builder
+const FLOW_FIELD_RADIUS: i32 = 16;
+
+/// Steps to a target tile from every tile of the region around it that can reach it, found by a
+/// breadth-first search out of the target. Every enemy heading for the target shares it.
+pub struct FlowField {
+    target: TileCoordinate,
+    distances: HashMap<TileCoordinate, u32>,
+}
+
+impl FlowField {
+    pub fn new(
+        target: TileCoordinate,
+        radius: i32,
+        is_passable: impl Fn(TileCoordinate) -> bool,
+    ) -> Self {
+        let region = TileRegion::around(target, radius, radius);
+        let mut distances = HashMap::new();
+        let mut frontier = VecDeque::new();
+        distances.insert(target, 0);
+        frontier.push_back(target);
+
+        while let Some(tile) = frontier.pop_front() {
+            let distance = distances[&tile];
+            for direction in ALL_DIRECTIONS.iter() {
+                let neighbour = tile.neighbour(*direction);
+                if region.contains(neighbour)
+                    && !distances.contains_key(&neighbour)
+                    && is_passable(neighbour)
+                {
+                    distances.insert(neighbour, distance + 1);
+                    frontier.push_back(neighbour);
+                }
+            }
+        }
+        FlowField { target, distances }
+    }
+
+    pub fn target(&self) -> TileCoordinate {
+        self.target
+    }
+
+    /// The adjacent tile to head for next on the way to the target. Tiles outside the field, and
+    /// the target itself, have none.
+    pub fn waypoint(&self, tile: TileCoordinate) -> Option<TileCoordinate> {
+        let next_distance = self.distances.get(&tile)?.checked_sub(1)?;
+        ALL_DIRECTIONS
+            .iter()
+            .map(|direction| tile.neighbour(*direction))
+            .find(|neighbour| self.distances.get(neighbour) == Some(&next_distance))
+    }
+}
+
+/// The flow field toward the builder, re-planned when the builder changes tiles or the tiles it
+/// was planned over change.
+#[derive(Default)]
+pub struct Pathfinding {
+    flow_field: Option<FlowField>,
+    // revisions of the world map and structure grid the field was planned with
+    planned_revisions: (u64, u64),
+}
+
+impl Pathfinding {
+    pub fn flow_field(&self) -> Option<&FlowField> {
+        self.flow_field.as_ref()
+    }
+
+    /// Drops the field, for when the world map is replaced.
+    pub fn reset(&mut self) {
+        self.flow_field = Option::None;
+    }
+}
+
+/// Whether enemies can walk over the tile. Tiles that aren't loaded are never walkable.
+pub fn is_passable(
+    tile: TileCoordinate,
+    world_map: &WorldMap,
+    structure_grid: &StructureGrid,
+) -> bool {
+    match world_map.get_tile(tile) {
+        Some(tile) => {
+            structure_grid.get(tile.coordinate()).is_none()
+                && !tile
+                    .resource
+                    .is_some_and(|node| !node.depleted && node.kind.blocks_movement())
+        }
+        None => false,
+    }
+}
+
+pub fn update_flow_field(
+    world_map: Res<WorldMap>,
+    structure_grid: Res<StructureGrid>,
+    rigid_body_set: Res<RigidBodySet>,
+    mut pathfinding: ResMut<Pathfinding>,
+    builder_query: Query<(&Builder, &RigidBodyHandleComponent)>,
+) {
+    let builder_tile = match builder_query.iter().next() {
+        Some((_builder, builder_body_handle)) => builder_tile(&rigid_body_set, builder_body_handle),
+        None => return,
+    };
+
+    let revisions = (world_map.revision(), structure_grid.revision());
+    let up_to_date = pathfinding.planned_revisions == revisions
+        && pathfinding
+            .flow_field
+            .as_ref()
+            .is_some_and(|flow_field| flow_field.target() == builder_tile);
+    if !up_to_date {
+        pathfinding.flow_field = Some(FlowField::new(builder_tile, FLOW_FIELD_RADIUS, |tile| {
+            is_passable(tile, &world_map, &structure_grid)
+        }));
+        pathfinding.planned_revisions = revisions;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flow_field_goes_around_walls() {
+        // a wall at x = 2 from y = -2 to y = 2, the target on its east side
+        let is_passable = |tile: TileCoordinate| !(tile.0 == 2 && tile.1 >= -2 && tile.1 <= 2);
+        let flow_field = FlowField::new(TileCoordinate(4, 0), 8, is_passable);
+
+        assert_eq!(Some(&0), flow_field.distances.get(&TileCoordinate(4, 0)));
+        assert_eq!(None, flow_field.distances.get(&TileCoordinate(2, 0)));
+        // straight through would be 4 steps, around the wall's end takes 10
+        assert_eq!(Some(&10), flow_field.distances.get(&TileCoordinate(0, 0)));
+
+        // following the waypoints reaches the target without entering the wall
+        let mut tile = TileCoordinate(0, 0);
+        let mut steps = 0;
+        while let Some(waypoint) = flow_field.waypoint(tile) {
+            assert!(is_passable(waypoint));
+            tile = waypoint;
+            steps += 1;
+        }
+        assert_eq!(TileCoordinate(4, 0), tile);
+        assert_eq!(10, steps);
+    }
+
+    #[test]
+    fn test_flow_field_unreachable_tiles() {
+        // the target is walled in on every side
+        let is_passable = |tile: TileCoordinate| tile.0.abs() != 1 && tile.1.abs() != 1;
+        let flow_field = FlowField::new(TileCoordinate(0, 0), 4, is_passable);
+
+        assert_eq!(None, flow_field.distances.get(&TileCoordinate(3, 0)));
+        assert_eq!(None, flow_field.waypoint(TileCoordinate(3, 0)));
+        assert_eq!(None, flow_field.waypoint(TileCoordinate(0, 0)));
+        // nothing past the radius is planned
+        let open_field = FlowField::new(TileCoordinate(0, 0), 4, |_tile| true);
+        assert_eq!(Some(&8), open_field.distances.get(&TileCoordinate(4, 4)));
+        assert_eq!(None, open_field.distances.get(&TileCoordinate(5, 0)));
+    }
+}
@@ -0,0 +1,153 @@
+use bevy::prelude::*;
+use bevy_rapier3d::physics::RigidBodyHandleComponent;
+use bevy_rapier3d::rapier::dynamics::RigidBodySet;
+
+use crate::asset_loader::AtlasHandles;
+use crate::builder::{Builder, BuilderMode};
+use crate::construction::{
+    builder_tile, check_placement, cursor_tile, PlacementError, PlacementState, Structure,
+    StructureType,
+};
+use crate::cursor::CursorState;
+use crate::data::Prefabs;
+use crate::inventory::Inventory;
+use crate::structure_grid::StructureGrid;
+use crate::world_map::WorldMap;
+
+// drawn above placed structures and the items riding them
+const GHOST_Z: f32 = 4.;
+
+fn valid_tint() -> Color {
+    Color::rgba(0.5, 1., 0.5, 0.6)
+}
+
+fn blocked_tint() -> Color {
+    Color::rgba(1., 0.4, 0.4, 0.6)
+}
+
+/// Green where the building can go, red wherever placing it would fail.
+fn placement_tint(placement: Result<(), PlacementError>) -> Color {
+    match placement {
+        Ok(()) => valid_tint(),
+        Err(_placement_error) => blocked_tint(),
+    }
+}
+
+/// The translucent copy of the selected building that follows the cursor in construct mode.
+#[derive(Default)]
+pub struct PlacementPreview {
+    ghost: Option<(Entity, StructureType)>,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_placement_preview(
+    mut commands: Commands,
+    atlas_handles: Res<AtlasHandles>,
+    prefabs: Res<Prefabs>,
+    cursor_state: Res<CursorState>,
+    placement_state: Res<PlacementState>,
+    structure_grid: Res<StructureGrid>,
+    world_map: Res<WorldMap>,
+    rigid_body_set: Res<RigidBodySet>,
+    mut placement_preview: ResMut<PlacementPreview>,
+    builder_query: Query<(&Builder, &RigidBodyHandleComponent, &Inventory)>,
+    mut ghost_query: Query<(&mut Transform, &mut TextureAtlasSprite)>,
+) {
+    let builder = builder_query
+        .iter()
+        .next()
+        .filter(|(builder, _builder_body_handle, _inventory)| {
+            builder.mode == BuilderMode::Construct
+        })
+        .map(|(_builder, builder_body_handle, inventory)| {
+            (
+                builder_tile(&rigid_body_set, builder_body_handle),
+                inventory,
+            )
+        });
+
+    let (builder_tile, inventory, map_tile) = match (builder, cursor_tile(&cursor_state)) {
+        (Some((builder_tile, inventory)), Some(map_tile)) => (builder_tile, inventory, map_tile),
+        _ => {
+            if let Some((ghost_entity, _structure_type)) = placement_preview.ghost.take() {
+                commands.despawn(ghost_entity);
+            }
+            return;
+        }
+    };
+
+    // the selection changed, so the ghost needs a different sprite
+    if let Some((ghost_entity, structure_type)) = placement_preview.ghost {
+        if structure_type != placement_state.selected {
+            commands.despawn(ghost_entity);
+            placement_preview.ghost = Option::None;
+        }
+    }
+
+    let structure = Structure::new(placement_state.selected, map_tile, placement_state.facing);
+    let mut transform = structure.sprite_transform();
+    (*transform.translation.z_mut()) = GHOST_Z;
+    let tint = placement_tint(check_placement(
+        &structure,
+        prefabs.cost(structure.structure_type),
+        builder_tile,
+        inventory,
+        &structure_grid,
+        &world_map,
+    ));
+
+    match placement_preview.ghost {
+        Some((ghost_entity, _structure_type)) => {
+            if let Ok(mut ghost_transform) =
+                ghost_query.get_component_mut::<Transform>(ghost_entity)
+            {
+                *ghost_transform = transform;
+            }
+            if let Ok(mut ghost_sprite) =
+                ghost_query.get_component_mut::<TextureAtlasSprite>(ghost_entity)
+            {
+                ghost_sprite.color = tint;
+            }
+        }
+        None => {
+            if let Some(atlas_id) = atlas_handles.get_asset(placement_state.selected.asset_type()) {
+                let mut sprite = TextureAtlasSprite::new(0);
+                sprite.color = tint;
+                let ghost_entity = commands
+                    .spawn(SpriteSheetComponents {
+                        texture_atlas: Handle::weak(atlas_id),
+                        sprite,
+                        transform,
+                        ..Default::default()
+                    })
+                    .current_entity()
+                    .unwrap();
+                placement_preview.ghost = Some((ghost_entity, placement_state.selected));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inventory::ItemStack;
+    use crate::item::Item;
+    use crate::resource_node::ResourceKind;
+
+    #[test]
+    fn test_placement_tint() {
+        assert_eq!(valid_tint(), placement_tint(Ok(())));
+        for placement_error in [
+            PlacementError::Occupied,
+            PlacementError::OutOfReach,
+            PlacementError::UnknownTerrain,
+            PlacementError::MissingItems(ItemStack::new(Item::Wood, 1)),
+            PlacementError::Blocked(ResourceKind::Tree),
+        ]
+        .iter()
+        {
+            assert_eq!(blocked_tint(), placement_tint(Err(*placement_error)));
+        }
+    }
+}
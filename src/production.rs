@@ -0,0 +1,131 @@
+use bevy::prelude::*;
+
+use crate::biome::Biome;
+use crate::construction::Structure;
+use crate::data::Prefabs;
+use crate::item::Item;
+use crate::logistics::{BeltItem, Conveyor};
+use crate::structure_grid::StructureGrid;
+use crate::world_map::{TileRegion, WorldMap};
+
+/// A building that periodically emits an item, chosen by the biome it stands on.
+#[derive(Default)]
+pub struct Producer {
+    // resolved once the terrain under the producer is loaded
+    production: Option<(Item, Timer)>,
+    // made but not yet taken by a conveyor, which pauses production
+    output: Option<Item>,
+}
+
+impl Producer {
+    /// Takes the item waiting to be output.
+    pub fn take_output(&mut self) -> Option<Item> {
+        self.output.take()
+    }
+}
+
+/// Returns the biome covering most of the footprint, or `None` if any of it isn't loaded.
+fn dominant_biome(world_map: &WorldMap, footprint: &TileRegion) -> Option<Biome> {
+    let mut biome_counts: Vec<(Biome, usize)> = Vec::new();
+    for tile_coordinate in footprint.coordinates() {
+        let biome = world_map.get_tile(tile_coordinate)?.biome;
+        match biome_counts
+            .iter_mut()
+            .find(|(counted, _)| *counted == biome)
+        {
+            Some((_, count)) => *count += 1,
+            None => biome_counts.push((biome, 1)),
+        }
+    }
+    // ties go to the biome found first, so the result doesn't depend on hashing
+    let mut dominant: Option<(Biome, usize)> = Option::None;
+    for (biome, count) in biome_counts {
+        if dominant.is_none_or(|(_, dominant_count)| count > dominant_count) {
+            dominant.replace((biome, count));
+        }
+    }
+    dominant.map(|(biome, _)| biome)
+}
+
+/// Runs every producer's timer, then pushes what they made onto an adjacent conveyor facing away
+/// from them.
+pub fn produce_items(
+    time: Res<Time>,
+    prefabs: Res<Prefabs>,
+    world_map: Res<WorldMap>,
+    structure_grid: Res<StructureGrid>,
+    mut producer_query: Query<(&Structure, &mut Producer)>,
+    mut conveyor_query: Query<&mut Conveyor>,
+) {
+    for (structure, mut producer) in producer_query.iter_mut() {
+        let footprint = structure.footprint();
+        if producer.production.is_none() {
+            if let Some(biome) = dominant_biome(&world_map, &footprint) {
+                let production_info = prefabs.production(biome);
+                producer.production.replace((
+                    production_info.item,
+                    Timer::from_seconds(production_info.seconds_per_item, true),
+                ));
+            }
+        }
+
+        if producer.output.is_none() {
+            if let Some((item, timer)) = producer.production.as_mut() {
+                timer.tick(time.delta_seconds);
+                if timer.finished {
+                    let item = *item;
+                    producer.output.replace(item);
+                }
+            }
+        }
+
+        if let Some(item) = producer.output {
+            for (output_tile, direction) in footprint.border() {
+                let conveyor_entity = match structure_grid.get(output_tile) {
+                    Some(conveyor_entity) => conveyor_entity,
+                    None => continue,
+                };
+                if let Ok(mut conveyor) =
+                    conveyor_query.get_component_mut::<Conveyor>(conveyor_entity)
+                {
+                    if conveyor.direction == direction && conveyor.accepts_from(direction) {
+                        conveyor
+                            .accept(BeltItem::new(item))
+                            .expect("the conveyor was checked to have room");
+                        producer.output = Option::None;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world_generator::WorldGenerator;
+    use crate::world_map::TileCoordinate;
+
+    #[test]
+    fn test_dominant_biome() {
+        let world_generator = WorldGenerator::new(11, 48., 0., 0.);
+        let mut world_map = WorldMap::new();
+        world_map.update_loaded_chunks(TileCoordinate(0, 0), &world_generator);
+
+        let single_tile = TileRegion {
+            min: TileCoordinate(4, 4),
+            max: TileCoordinate(4, 4),
+        };
+        assert_eq!(
+            Some(world_generator.biome_at(4, 4)),
+            dominant_biome(&world_map, &single_tile)
+        );
+
+        let unloaded = TileRegion {
+            min: TileCoordinate(900, 900),
+            max: TileCoordinate(901, 901),
+        };
+        assert_eq!(None, dominant_biome(&world_map, &unloaded));
+    }
+}
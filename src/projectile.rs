@@ -1,12 +1,16 @@
-use crate::data;
+use crate::animation::UnitType;
+use crate::data::Prefabs;
+use bevy::asset::HandleId;
 use bevy::prelude::*;
+use bevy_rapier3d::rapier::dynamics::RigidBodyBuilder;
+use serde::Deserialize;
 
-#[derive(PartialEq, Eq, Copy, Clone, Hash)]
+#[derive(Deserialize, PartialEq, Eq, Copy, Clone, Hash, Debug)]
 pub enum ProjectileType {
     Arrow,
 }
 
-#[derive(Clone)]
+#[derive(Deserialize, Clone)]
 pub struct Projectile {
     pub damage: i32,
     pub ttl: f32,
@@ -15,17 +19,40 @@ pub struct Projectile {
 }
 
 impl Projectile {
-    pub fn new(damage: i32, ttl: f32, piercing: bool, speed: f32) -> Self {
-        Projectile {
-            damage,
-            ttl,
-            piercing,
-            speed,
-        }
+    pub fn arrow(prefabs: &Prefabs) -> Projectile {
+        prefabs.projectile(ProjectileType::Arrow)
     }
+}
+
+/// Spawns the arrows of one shot, as laid out by `builder::compute_projectile_positions`.
+pub fn spawn_projectiles(
+    commands: &mut Commands,
+    arrow_id: HandleId,
+    prefabs: &Prefabs,
+    projectile: &Projectile,
+    projectile_positions: Vec<(Transform, RigidBodyBuilder)>,
+) {
+    for (transform, body) in projectile_positions {
+        let arrow_atlas_handle = Handle::weak(arrow_id);
+        let projectile_timer = Timer::from_seconds(projectile.ttl, false);
+
+        let arrow_entity = commands
+            .spawn(SpriteSheetComponents {
+                texture_atlas: arrow_atlas_handle,
+                sprite: TextureAtlasSprite::new(0),
+                transform,
+                ..Default::default()
+            })
+            .with(projectile.clone())
+            .with(projectile_timer)
+            .current_entity()
+            .unwrap();
+
+        let arrow_collider = prefabs
+            .collider(UnitType::Arrow)
+            .user_data(arrow_entity.to_bits() as u128);
 
-    pub fn arrow() -> Projectile {
-        data::get_projectile_info(ProjectileType::Arrow)
+        commands.insert(arrow_entity, (body, arrow_collider));
     }
 }
 
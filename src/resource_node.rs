@@ -0,0 +1,479 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_rapier3d::physics::RigidBodyHandleComponent;
+use bevy_rapier3d::rapier::dynamics::RigidBodySet;
+use serde::{Deserialize, Serialize};
+
+use crate::animation::{Animated, AnimationState};
+use crate::asset_loader::AtlasHandles;
+use crate::biome::Biome;
+use crate::builder::Builder;
+use crate::construction::{builder_tile, cursor_tile};
+use crate::cursor::CursorState;
+use crate::data::{AssetType, Prefabs, ResourceInfo};
+use crate::input_bindings::InputBindings;
+use crate::inventory::Inventory;
+use crate::world_map::{tile_to_position, TileCoordinate, TileRegion, WorldMap};
+
+// how far from the builder's tile a node can be harvested
+const HARVEST_REACH: i32 = 2;
+
+// nodes are drawn with the item sprite, blown up to nearly fill their tile
+const RESOURCE_NODE_SCALE: f32 = 3.;
+const RESOURCE_NODE_Z: f32 = 0.5;
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Copy, Clone, Hash, Debug)]
+pub enum ResourceKind {
+    Tree,
+    Rock,
+    Dune,
+}
+
+pub const ALL_RESOURCE_KINDS: [ResourceKind; 3] =
+    [ResourceKind::Tree, ResourceKind::Rock, ResourceKind::Dune];
+
+impl ResourceKind {
+    /// The kind of node world generation scatters over the biome.
+    pub fn of_biome(biome: Biome) -> Self {
+        match biome {
+            Biome::Grassland => ResourceKind::Tree,
+            Biome::Rockland => ResourceKind::Rock,
+            Biome::Desert => ResourceKind::Dune,
+        }
+    }
+
+    /// Whether enemies have to walk around the node. Dunes can be crossed.
+    pub fn blocks_movement(&self) -> bool {
+        match self {
+            ResourceKind::Tree | ResourceKind::Rock => true,
+            ResourceKind::Dune => false,
+        }
+    }
+
+    fn tint(&self) -> Color {
+        match self {
+            ResourceKind::Tree => Color::rgb(0.3, 0.7, 0.3),
+            ResourceKind::Rock => Color::rgb(0.5, 0.5, 0.55),
+            ResourceKind::Dune => Color::rgb(0.9, 0.8, 0.5),
+        }
+    }
+}
+
+/// A tree, rock or dune on a tile that gives a limited number of items when harvested. The
+/// resource prefabs say which item, how many and whether the node grows back.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Copy, Clone, Debug)]
+pub struct ResourceNode {
+    pub kind: ResourceKind,
+    // items taken since the node was generated or last grew back
+    pub harvested: u32,
+    pub depleted: bool,
+}
+
+impl ResourceNode {
+    pub fn new(kind: ResourceKind) -> Self {
+        ResourceNode {
+            kind,
+            harvested: 0,
+            depleted: false,
+        }
+    }
+
+    /// Counts one more item taken, depleting the node once `total_yield` have been.
+    fn take_item(&mut self, total_yield: u32) {
+        self.harvested += 1;
+        if self.harvested >= total_yield {
+            self.depleted = true;
+        }
+    }
+}
+
+/// Sprites of the resource nodes in view and the regrowth timers of depleted nodes.
+#[derive(Default)]
+pub struct ResourceNodes {
+    sprites: HashMap<TileCoordinate, Entity>,
+    regrowing: HashMap<TileCoordinate, Timer>,
+}
+
+impl ResourceNodes {
+    /// Despawns every node sprite and forgets the regrowth timers, for when the world map is
+    /// replaced. Nodes are shown again as their tiles come into view.
+    pub fn clear(&mut self, commands: &mut Commands) {
+        for (_coordinate, sprite_entity) in self.sprites.drain() {
+            commands.despawn(sprite_entity);
+        }
+        self.regrowing.clear();
+    }
+
+    /// Advances the regrowth timers and restores the nodes whose time is up, returning their
+    /// tiles. Nodes in chunks that have been unloaded wait until they are loaded again.
+    fn regrow(&mut self, delta_seconds: f32, world_map: &mut WorldMap) -> Vec<TileCoordinate> {
+        let mut regrown = Vec::new();
+        for (coordinate, timer) in self.regrowing.iter_mut() {
+            timer.tick(delta_seconds);
+            if timer.finished {
+                if let Some(node) = world_map
+                    .get_tile(*coordinate)
+                    .and_then(|tile| tile.resource)
+                {
+                    world_map.set_resource_node(*coordinate, ResourceNode::new(node.kind));
+                    regrown.push(*coordinate);
+                }
+            }
+        }
+        for coordinate in regrown.iter() {
+            self.regrowing.remove(coordinate);
+        }
+        regrown
+    }
+}
+
+/// The node being harvested and the time until it gives its next item.
+#[derive(Default)]
+pub struct HarvestState {
+    target: Option<(TileCoordinate, Timer)>,
+    // whether the builder was told there's no room for the node's item, so it's said only once
+    inventory_full: bool,
+}
+
+impl HarvestState {
+    pub fn reset(&mut self) {
+        self.target = Option::None;
+        self.inventory_full = false;
+    }
+}
+
+/// Spawns the sprite of the tile's resource node as the tile comes into view.
+pub fn show_resource_node(
+    commands: &mut Commands,
+    atlas_handles: &AtlasHandles,
+    prefabs: &Prefabs,
+    resource_nodes: &mut ResourceNodes,
+    world_map: &WorldMap,
+    coordinate: TileCoordinate,
+) {
+    let node = match world_map
+        .get_tile(coordinate)
+        .and_then(|tile| tile.resource)
+    {
+        Some(node) => node,
+        None => return,
+    };
+    if node.depleted {
+        // regrowth timers are dropped on load, so restart them for the nodes seen again
+        if let Some(regrowth_seconds) = prefabs.resource(node.kind).regrowth_seconds {
+            resource_nodes
+                .regrowing
+                .entry(coordinate)
+                .or_insert_with(|| Timer::from_seconds(regrowth_seconds, false));
+        }
+        return;
+    }
+    if resource_nodes.sprites.contains_key(&coordinate) {
+        return;
+    }
+    if let Some(atlas_id) = atlas_handles.get_asset(AssetType::Wood) {
+        let mut transform = tile_to_position(coordinate);
+        *transform.translation.z_mut() = RESOURCE_NODE_Z;
+        transform.scale = Vec3::new(RESOURCE_NODE_SCALE, RESOURCE_NODE_SCALE, 1.);
+        let mut sprite = TextureAtlasSprite::new(0);
+        sprite.color = node.kind.tint();
+        if let Some(sprite_entity) = commands
+            .spawn(SpriteSheetComponents {
+                texture_atlas: Handle::weak(atlas_id),
+                sprite,
+                transform,
+                ..Default::default()
+            })
+            .current_entity()
+        {
+            resource_nodes.sprites.insert(coordinate, sprite_entity);
+        }
+    }
+}
+
+/// Despawns the sprite of the tile's resource node as the tile leaves the view.
+pub fn hide_resource_node(
+    commands: &mut Commands,
+    resource_nodes: &mut ResourceNodes,
+    coordinate: TileCoordinate,
+) {
+    if let Some(sprite_entity) = resource_nodes.sprites.remove(&coordinate) {
+        commands.despawn(sprite_entity);
+    }
+}
+
+/// Harvests the resource node under the cursor while the interact action is held on it and the
+/// builder stands close enough. The builder swings at the node and gets one of its items every
+/// few moments, until the node is depleted and disappears.
+#[allow(clippy::too_many_arguments)]
+pub fn harvest_resources(
+    mut commands: Commands,
+    time: Res<Time>,
+    input_bindings: Res<InputBindings>,
+    prefabs: Res<Prefabs>,
+    cursor_state: Res<CursorState>,
+    rigid_body_set: Res<RigidBodySet>,
+    mut world_map: ResMut<WorldMap>,
+    mut resource_nodes: ResMut<ResourceNodes>,
+    mut harvest_state: ResMut<HarvestState>,
+    mut builder_query: Query<(
+        &mut Timer,
+        &mut Builder,
+        &mut Animated,
+        &RigidBodyHandleComponent,
+        &mut Inventory,
+    )>,
+) {
+    let (mut builder_timer, mut builder, mut animated, builder_body_handle, mut inventory) =
+        match builder_query.iter_mut().next() {
+            Some(builder) => builder,
+            None => return,
+        };
+
+    let mut target = Option::None;
+    if let Some(map_tile) = cursor_tile(&cursor_state) {
+        let reach = TileRegion::around(
+            builder_tile(&rigid_body_set, builder_body_handle),
+            HARVEST_REACH,
+            HARVEST_REACH,
+        );
+        let has_node = world_map
+            .get_tile(map_tile)
+            .and_then(|tile| tile.resource)
+            .is_some_and(|node| !node.depleted);
+        if input_bindings.action_pressed("interact") && reach.contains(map_tile) && has_node {
+            target = Some(map_tile);
+        }
+    }
+
+    // moving off a node restarts the swing on the next one
+    let current_target = harvest_state
+        .target
+        .as_ref()
+        .map(|(coordinate, _timer)| *coordinate);
+    if current_target != target {
+        harvest_state.inventory_full = false;
+        harvest_state.target = target.map(|coordinate| {
+            let node = world_map.get_tile(coordinate).unwrap().resource.unwrap();
+            let seconds_per_item = prefabs.resource(node.kind).seconds_per_item;
+            (coordinate, Timer::from_seconds(seconds_per_item, true))
+        });
+    }
+    builder.harvesting = target;
+
+    let (coordinate, timer) = match harvest_state.target.as_mut() {
+        Some(target) => target,
+        None => return,
+    };
+    if animated.state != AnimationState::Attack {
+        animated.state = AnimationState::Attack;
+        animated.animation_index = 0;
+        builder_timer.reset();
+        builder_timer.finished = true;
+    }
+
+    timer.tick(time.delta_seconds);
+    if !timer.just_finished {
+        return;
+    }
+    let coordinate = *coordinate;
+    let node = world_map.get_tile(coordinate).unwrap().resource.unwrap();
+    let resource_info = prefabs.resource(node.kind);
+    if inventory.room_for(resource_info.item) == 0 {
+        if !harvest_state.inventory_full {
+            println!("No room for more {:?}", resource_info.item);
+            harvest_state.inventory_full = true;
+        }
+        return;
+    }
+    harvest_state.inventory_full = false;
+
+    let node = harvest_item(
+        &mut world_map,
+        &mut resource_nodes,
+        &mut inventory,
+        &resource_info,
+        coordinate,
+        node,
+    );
+    if node.depleted {
+        println!("Depleted {:?}", node.kind);
+        hide_resource_node(&mut commands, &mut resource_nodes, coordinate);
+        harvest_state.reset();
+        builder.harvesting = Option::None;
+    }
+}
+
+/// Moves one of the node's items into the inventory and returns the node as left on the tile.
+/// Taking the last item depletes the node and starts it growing back, if it does.
+fn harvest_item(
+    world_map: &mut WorldMap,
+    resource_nodes: &mut ResourceNodes,
+    inventory: &mut Inventory,
+    resource_info: &ResourceInfo,
+    coordinate: TileCoordinate,
+    mut node: ResourceNode,
+) -> ResourceNode {
+    inventory.add(resource_info.item, 1);
+    node.take_item(resource_info.total_yield);
+    world_map.set_resource_node(coordinate, node);
+    if node.depleted {
+        if let Some(regrowth_seconds) = resource_info.regrowth_seconds {
+            resource_nodes
+                .regrowing
+                .insert(coordinate, Timer::from_seconds(regrowth_seconds, false));
+        }
+    }
+    node
+}
+
+/// Restores depleted nodes once their regrowth time is up, showing the ones in view.
+pub fn regrow_resource_nodes(
+    mut commands: Commands,
+    time: Res<Time>,
+    atlas_handles: Res<AtlasHandles>,
+    prefabs: Res<Prefabs>,
+    mut world_map: ResMut<WorldMap>,
+    mut resource_nodes: ResMut<ResourceNodes>,
+) {
+    let regrown = resource_nodes.regrow(time.delta_seconds, &mut world_map);
+    let visible_region = world_map.visible_region();
+    for coordinate in regrown {
+        if visible_region.is_some_and(|region| region.contains(coordinate)) {
+            show_resource_node(
+                &mut commands,
+                &atlas_handles,
+                &prefabs,
+                &mut resource_nodes,
+                &world_map,
+                coordinate,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::Item;
+    use crate::world_generator::WorldGenerator;
+
+    const TREE: ResourceInfo = ResourceInfo {
+        item: Item::Wood,
+        total_yield: 3,
+        seconds_per_item: 1.,
+        regrowth_seconds: Some(10.),
+    };
+
+    fn map_with_tree(coordinate: TileCoordinate) -> WorldMap {
+        let mut world_map = WorldMap::new();
+        world_map.update_loaded_chunks(TileCoordinate(0, 0), &WorldGenerator::new(0, 48., 0., 0.));
+        world_map.set_resource_node(coordinate, ResourceNode::new(ResourceKind::Tree));
+        world_map
+    }
+
+    fn harvest(
+        world_map: &mut WorldMap,
+        resource_nodes: &mut ResourceNodes,
+        inventory: &mut Inventory,
+        coordinate: TileCoordinate,
+    ) -> ResourceNode {
+        let node = world_map.get_tile(coordinate).unwrap().resource.unwrap();
+        harvest_item(
+            world_map,
+            resource_nodes,
+            inventory,
+            &TREE,
+            coordinate,
+            node,
+        )
+    }
+
+    #[test]
+    fn test_harvest_takes_one_item() {
+        let coordinate = TileCoordinate(2, 3);
+        let mut world_map = map_with_tree(coordinate);
+        let mut resource_nodes = ResourceNodes::default();
+        let mut inventory = Inventory::new(4);
+        let revision = world_map.revision();
+
+        harvest(
+            &mut world_map,
+            &mut resource_nodes,
+            &mut inventory,
+            coordinate,
+        );
+        let node = harvest(
+            &mut world_map,
+            &mut resource_nodes,
+            &mut inventory,
+            coordinate,
+        );
+        assert_eq!(2, node.harvested);
+        assert!(!node.depleted);
+        assert_eq!(Some(node), world_map.get_tile(coordinate).unwrap().resource);
+        assert_eq!(2, inventory.count(Item::Wood));
+        // paths don't care how much is left on a node
+        assert_eq!(revision, world_map.revision());
+        assert!(resource_nodes.regrowing.is_empty());
+    }
+
+    #[test]
+    fn test_harvest_depletes_node() {
+        let coordinate = TileCoordinate(2, 3);
+        let mut world_map = map_with_tree(coordinate);
+        let mut resource_nodes = ResourceNodes::default();
+        let mut inventory = Inventory::new(4);
+        let revision = world_map.revision();
+
+        for _ in 0..TREE.total_yield {
+            harvest(
+                &mut world_map,
+                &mut resource_nodes,
+                &mut inventory,
+                coordinate,
+            );
+        }
+        let node = world_map.get_tile(coordinate).unwrap().resource.unwrap();
+        assert!(node.depleted);
+        assert_eq!(TREE.total_yield, inventory.count(Item::Wood));
+        assert_eq!(revision + 1, world_map.revision());
+        assert!(resource_nodes.regrowing.contains_key(&coordinate));
+    }
+
+    #[test]
+    fn test_depleted_node_regrows() {
+        let coordinate = TileCoordinate(2, 3);
+        let mut world_map = map_with_tree(coordinate);
+        let mut resource_nodes = ResourceNodes::default();
+        let mut inventory = Inventory::new(4);
+        for _ in 0..TREE.total_yield {
+            harvest(
+                &mut world_map,
+                &mut resource_nodes,
+                &mut inventory,
+                coordinate,
+            );
+        }
+        let revision = world_map.revision();
+
+        assert!(resource_nodes.regrow(9., &mut world_map).is_empty());
+        assert!(
+            world_map
+                .get_tile(coordinate)
+                .unwrap()
+                .resource
+                .unwrap()
+                .depleted
+        );
+
+        assert_eq!(vec![coordinate], resource_nodes.regrow(1., &mut world_map));
+        assert_eq!(
+            Some(ResourceNode::new(ResourceKind::Tree)),
+            world_map.get_tile(coordinate).unwrap().resource
+        );
+        assert_eq!(revision + 1, world_map.revision());
+        assert!(resource_nodes.regrowing.is_empty());
+    }
+}
@@ -0,0 +1,400 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy_rapier3d::physics::RigidBodyHandleComponent;
+use bevy_rapier3d::rapier::dynamics::RigidBodySet;
+use bevy_rapier3d::rapier::na::Isometry3;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::asset_loader::AtlasHandles;
+use crate::builder::{Builder, BuilderMode};
+use crate::construction::{spawn_structure, Structure};
+use crate::enemy::Enemy;
+use crate::enemy_registry::{EnemyRecord, EnemyRegistry};
+use crate::health::Health;
+use crate::input_bindings::InputBindings;
+use crate::inventory::Inventory;
+use crate::logistics::ItemSprite;
+use crate::pathfinding::Pathfinding;
+use crate::resource_node::{HarvestState, ResourceNodes};
+use crate::save_migration::migrate;
+use crate::structure_grid::StructureGrid;
+use crate::world_generator::WorldGenerator;
+use crate::world_map::{ChunkCoordinate, Tile, WorldMap};
+
+pub const QUICK_SAVE_PATH: &str = "saves/quicksave.ron";
+
+/// Bumped whenever `SaveGame` changes shape; older files go through `migrate`.
+pub const SAVE_FORMAT_VERSION: u32 = 7;
+
+pub struct SaveGamePlugin;
+
+impl Plugin for SaveGamePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(quick_save.system())
+            .add_system(quick_load.system());
+    }
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(PathBuf, std::io::Error),
+    Malformed(ron::Error),
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(path, err) => write!(f, "could not access {}: {}", path.display(), err),
+            SaveError::Malformed(err) => write!(f, "malformed save file: {}", err),
+            SaveError::UnsupportedVersion(version) => write!(
+                f,
+                "save format version {} is not supported, expected at most {}",
+                version, SAVE_FORMAT_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<ron::Error> for SaveError {
+    fn from(err: ron::Error) -> Self {
+        SaveError::Malformed(err)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BuilderState {
+    pub position: (f32, f32),
+    pub mode: BuilderMode,
+    pub inventory: Inventory,
+    // none when migrated from before the builder could be hurt, which loads at full health
+    pub hp: Option<i32>,
+}
+
+/// Everything needed to rebuild a game session.
+///
+/// Terrain is regenerated from the seed, so only the chunks that were generated and the tiles
+/// that have changed since are stored.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SaveGame {
+    pub version: u32,
+    pub seed: u64,
+    pub generated_chunks: Vec<ChunkCoordinate>,
+    pub modified_tiles: Vec<Tile>,
+    pub structures: Vec<Structure>,
+    pub builder: BuilderState,
+    pub next_enemy_id: u64,
+    pub enemies: Vec<EnemyRecord>,
+}
+
+// read before the rest of the file to pick a migration
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+impl SaveGame {
+    pub fn capture(
+        world_generator: &WorldGenerator,
+        world_map: &WorldMap,
+        enemy_registry: &EnemyRegistry,
+        structures: Vec<Structure>,
+        builder: BuilderState,
+    ) -> SaveGame {
+        SaveGame {
+            version: SAVE_FORMAT_VERSION,
+            seed: world_generator.seed,
+            generated_chunks: world_map.generated_chunks(),
+            modified_tiles: world_map.modified_tiles(world_generator),
+            structures,
+            builder,
+            next_enemy_id: enemy_registry.next_id(),
+            enemies: enemy_registry.records().cloned().collect(),
+        }
+    }
+
+    pub fn to_ron(&self) -> Result<String, SaveError> {
+        Ok(ron::ser::to_string_pretty(self, PrettyConfig::default())?)
+    }
+
+    pub fn from_ron(source: &str) -> Result<SaveGame, SaveError> {
+        let header: SaveHeader = ron::from_str(source)?;
+        migrate(header.version, source)
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), SaveError> {
+        let path = path.as_ref();
+        let source = self.to_ron()?;
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).map_err(|err| SaveError::Io(path.to_owned(), err))?;
+        }
+        fs::write(path, source).map_err(|err| SaveError::Io(path.to_owned(), err))
+    }
+
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<SaveGame, SaveError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(|err| SaveError::Io(path.to_owned(), err))?;
+        SaveGame::from_ron(&source)
+    }
+
+    pub fn restore_world_map(&self, world_generator: &WorldGenerator) -> WorldMap {
+        WorldMap::restore(
+            world_generator,
+            &self.generated_chunks,
+            self.modified_tiles.clone(),
+        )
+    }
+
+    pub fn restore_enemy_registry(&self) -> EnemyRegistry {
+        EnemyRegistry::restore(self.next_enemy_id, self.enemies.clone())
+    }
+}
+
+fn quick_save(
+    input_bindings: Res<InputBindings>,
+    world_generator: Res<WorldGenerator>,
+    world_map: Res<WorldMap>,
+    enemy_registry: Res<EnemyRegistry>,
+    rigid_body_set: Res<RigidBodySet>,
+    builder_query: Query<(&Builder, &RigidBodyHandleComponent, &Inventory, &Health)>,
+    structure_query: Query<&Structure>,
+) {
+    if !input_bindings.action_just_pressed("quick_save") {
+        return;
+    }
+    let (builder, builder_body_handle, inventory, health) = match builder_query.iter().next() {
+        Some(builder) => builder,
+        None => {
+            eprintln!("Warning: nothing to save before the builder has spawned");
+            return;
+        }
+    };
+    let builder_translation = rigid_body_set
+        .get(builder_body_handle.handle())
+        .unwrap()
+        .position()
+        .translation;
+
+    let save_game = SaveGame::capture(
+        &world_generator,
+        &world_map,
+        &enemy_registry,
+        structure_query.iter().cloned().collect(),
+        BuilderState {
+            position: (builder_translation.x, builder_translation.y),
+            mode: builder.mode,
+            inventory: inventory.clone(),
+            hp: Some(health.hp),
+        },
+    );
+    match save_game.write(QUICK_SAVE_PATH) {
+        Ok(()) => println!("Saved game to {}", QUICK_SAVE_PATH),
+        Err(err) => eprintln!("Warning: could not save game: {}", err),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn quick_load(
+    mut commands: Commands,
+    input_bindings: Res<InputBindings>,
+    atlas_handles: Res<AtlasHandles>,
+    mut world_generator: ResMut<WorldGenerator>,
+    mut world_map: ResMut<WorldMap>,
+    mut enemy_registry: ResMut<EnemyRegistry>,
+    mut structure_grid: ResMut<StructureGrid>,
+    mut resource_nodes: ResMut<ResourceNodes>,
+    mut harvest_state: ResMut<HarvestState>,
+    mut pathfinding: ResMut<Pathfinding>,
+    mut rigid_body_set: ResMut<RigidBodySet>,
+    mut builder_query: Query<(
+        &mut Builder,
+        &RigidBodyHandleComponent,
+        &mut Inventory,
+        &mut Health,
+    )>,
+    structure_query: Query<(Entity, &Structure)>,
+    item_sprite_query: Query<(Entity, &ItemSprite)>,
+    enemy_query: Query<(Entity, &Enemy)>,
+) {
+    if !input_bindings.action_just_pressed("quick_load") {
+        return;
+    }
+    let save_game = match SaveGame::read(QUICK_SAVE_PATH) {
+        Ok(save_game) => save_game,
+        Err(err) => {
+            eprintln!("Warning: could not load game: {}", err);
+            return;
+        }
+    };
+    let (mut builder, builder_body_handle, mut inventory, mut health) =
+        match builder_query.iter_mut().next() {
+            Some(builder) => builder,
+            None => {
+                eprintln!("Warning: cannot load a game before the builder has spawned");
+                return;
+            }
+        };
+
+    let builder_body = rigid_body_set
+        .get_mut(builder_body_handle.handle())
+        .unwrap();
+    let (builder_x, builder_y) = save_game.builder.position;
+    let builder_z = builder_body.position().translation.z;
+    builder_body.set_position(
+        Isometry3::translation(builder_x, builder_y, builder_z),
+        true,
+    );
+    builder.mode = save_game.builder.mode;
+    *inventory = save_game.builder.inventory.clone();
+    health.hp = save_game.builder.hp.unwrap_or(health.max_hp);
+
+    world_generator.seed = save_game.seed;
+    for entity in world_map.terrain_entities() {
+        commands.despawn(entity);
+    }
+    *world_map = save_game.restore_world_map(&world_generator);
+    resource_nodes.clear(&mut commands);
+    harvest_state.reset();
+    pathfinding.reset();
+
+    // the registry is restored with every enemy dormant, they are respawned as their tiles come
+    // into view
+    for (enemy_entity, _enemy) in enemy_query.iter() {
+        commands.despawn(enemy_entity);
+    }
+    *enemy_registry = save_game.restore_enemy_registry();
+
+    for (structure_entity, _structure) in structure_query.iter() {
+        commands.despawn(structure_entity);
+    }
+    for (item_sprite_entity, _item_sprite) in item_sprite_query.iter() {
+        commands.despawn(item_sprite_entity);
+    }
+    structure_grid.clear();
+    for structure in save_game.structures {
+        spawn_structure(
+            &mut commands,
+            &atlas_handles,
+            &mut structure_grid,
+            structure,
+        );
+    }
+
+    println!("Loaded game from {}", QUICK_SAVE_PATH);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::CardinalDirection;
+    use crate::builder::BUILDER_INVENTORY_SLOTS;
+    use crate::construction::StructureType;
+    use crate::enemy::EnemyType;
+    use crate::item::Item;
+    use crate::resource_node::{ResourceKind, ResourceNode};
+    use crate::world_map::{tile_to_position, TileCoordinate};
+
+    #[test]
+    fn test_save_round_trip() {
+        let world_generator = WorldGenerator::new(42, 48., 0.001, 0.);
+        let mut world_map = WorldMap::new();
+        world_map.update_loaded_chunks(TileCoordinate(0, 0), &world_generator);
+        world_map.take_enemy_spawns();
+        assert!(world_map.modified_tiles(&world_generator).is_empty());
+
+        let harvested_node = ResourceNode {
+            kind: ResourceKind::Rock,
+            harvested: 3,
+            depleted: false,
+        };
+        let tile = world_map.get_tile_mut(TileCoordinate(5, 5)).unwrap();
+        tile.variant = 100;
+        tile.resource = Some(harvested_node);
+        // store the modified chunk, as happens when the builder walks away from it
+        world_map.update_loaded_chunks(TileCoordinate(1000, 0), &world_generator);
+        world_map.take_enemy_spawns();
+
+        let mut enemy_registry = EnemyRegistry::default();
+        enemy_registry.register(TileCoordinate(3, 3), EnemyType::Scorpion, 50);
+        let wounded_id = enemy_registry.register(TileCoordinate(-4, 2), EnemyType::Golem, 200);
+        enemy_registry.get_mut(wounded_id).unwrap().hp = 25;
+
+        let mut builder_inventory = Inventory::new(BUILDER_INVENTORY_SLOTS);
+        builder_inventory.add(Item::Stone, 7);
+
+        let save_game = SaveGame::capture(
+            &world_generator,
+            &world_map,
+            &enemy_registry,
+            vec![Structure::new(
+                StructureType::Conveyor,
+                TileCoordinate(1, 2),
+                CardinalDirection::South,
+            )],
+            BuilderState {
+                position: (16., -32.),
+                mode: BuilderMode::Construct,
+                inventory: builder_inventory.clone(),
+                hp: Some(35),
+            },
+        );
+        let loaded = SaveGame::from_ron(&save_game.to_ron().unwrap()).unwrap();
+
+        assert_eq!(42, loaded.seed);
+        assert_eq!(1, loaded.structures.len());
+        assert_eq!(TileCoordinate(1, 2), loaded.structures[0].tile);
+        assert_eq!(CardinalDirection::South, loaded.structures[0].facing);
+        assert_eq!((16., -32.), loaded.builder.position);
+        assert_eq!(BuilderMode::Construct, loaded.builder.mode);
+        assert_eq!(builder_inventory, loaded.builder.inventory);
+        assert_eq!(Some(35), loaded.builder.hp);
+
+        let loaded_generator = WorldGenerator::new(loaded.seed, 48., 0.001, 0.);
+        let mut loaded_map = loaded.restore_world_map(&loaded_generator);
+        assert_eq!(
+            world_map.generated_chunks().len(),
+            loaded_map.generated_chunks().len()
+        );
+        loaded_map.update_loaded_chunks(TileCoordinate(0, 0), &loaded_generator);
+        let loaded_tile = loaded_map.get_tile(TileCoordinate(5, 5)).unwrap();
+        assert_eq!(100, loaded_tile.variant);
+        assert_eq!(Some(harvested_node), loaded_tile.resource);
+        assert_eq!(
+            loaded_generator.biome_at(-7, 9),
+            loaded_map.get_tile(TileCoordinate(-7, 9)).unwrap().biome
+        );
+        // enemies of restored chunks come back through the registry, not world generation
+        assert!(loaded_map.take_enemy_spawns().is_empty());
+
+        let mut loaded_registry = loaded.restore_enemy_registry();
+        let woken = loaded_registry.wake_at(TileCoordinate(-4, 2));
+        assert_eq!(1, woken.len());
+        assert_eq!(25, woken[0].hp);
+        assert_eq!(EnemyType::Golem, woken[0].enemy_type);
+        let position = tile_to_position(TileCoordinate(-4, 2)).translation;
+        assert_eq!((position.x(), position.y()), woken[0].position);
+        assert_eq!(
+            enemy_registry.next_id(),
+            loaded_registry
+                .register(TileCoordinate(0, 0), EnemyType::Goblin, 80)
+                .0
+        );
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let source = format!("(version: {}, seed: 1)", SAVE_FORMAT_VERSION + 1);
+        match SaveGame::from_ron(&source) {
+            Err(SaveError::UnsupportedVersion(version)) => {
+                assert_eq!(SAVE_FORMAT_VERSION + 1, version)
+            }
+            other => panic!("expected an unsupported version error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,379 @@
+use serde::Deserialize;
+
+use crate::animation::CardinalDirection;
+use crate::biome::Biome;
+use crate::builder::{BuilderMode, BUILDER_INVENTORY_SLOTS};
+use crate::construction::{Structure, StructureType};
+use crate::enemy::EnemyType;
+use crate::enemy_registry::{EnemyId, EnemyRecord};
+use crate::inventory::Inventory;
+use crate::save_game::{BuilderState, SaveError, SaveGame, SAVE_FORMAT_VERSION};
+use crate::world_map::{ChunkCoordinate, Tile, TileCoordinate};
+
+/// Reads a save written with the given format version into the current `SaveGame`.
+///
+/// Older saves are read with the layout they were written in, and the parts that changed since
+/// are converted to the current ones. When the format changes, bump `SAVE_FORMAT_VERSION`, keep
+/// the previous shape of what changed as its own struct, and add a layout and an arm for the old
+/// version here.
+pub fn migrate(version: u32, source: &str) -> Result<SaveGame, SaveError> {
+    match version {
+        1 => Ok(ron::from_str::<SaveV1>(source)?.into()),
+        2 => Ok(ron::from_str::<SaveV2>(source)?.into()),
+        3 => Ok(ron::from_str::<SaveV3>(source)?.into()),
+        4 => Ok(ron::from_str::<SaveV4>(source)?.into()),
+        5 => Ok(ron::from_str::<SaveV5>(source)?.into()),
+        6 => Ok(ron::from_str::<SaveV6>(source)?.into()),
+        SAVE_FORMAT_VERSION => Ok(ron::from_str(source)?),
+        _ => Err(SaveError::UnsupportedVersion(version)),
+    }
+}
+
+type SaveV1 = SaveLayout<TileV1, StructureV1, BuilderStateV1, EnemyRecordV1>;
+type SaveV2 = SaveLayout<TileV1, Structure, BuilderStateV1, EnemyRecordV1>;
+type SaveV3 = SaveLayout<TileV1, Structure, BuilderStateV3, EnemyRecordV1>;
+type SaveV4 = SaveLayout<Tile, Structure, BuilderStateV3, EnemyRecordV1>;
+type SaveV5 = SaveLayout<Tile, Structure, BuilderState, EnemyRecordV1>;
+type SaveV6 = SaveLayout<Tile, Structure, BuilderState, EnemyRecordV6>;
+
+/// The fields of `SaveGame`, with the ones whose shape changed between versions left open.
+#[derive(Deserialize)]
+struct SaveLayout<T, S, B, E> {
+    seed: u64,
+    generated_chunks: Vec<ChunkCoordinate>,
+    modified_tiles: Vec<T>,
+    structures: Vec<S>,
+    builder: B,
+    next_enemy_id: u64,
+    enemies: Vec<E>,
+}
+
+impl<T, S, B, E> From<SaveLayout<T, S, B, E>> for SaveGame
+where
+    Tile: From<T>,
+    Structure: From<S>,
+    BuilderState: From<B>,
+    EnemyRecord: From<E>,
+{
+    fn from(old: SaveLayout<T, S, B, E>) -> SaveGame {
+        SaveGame {
+            version: SAVE_FORMAT_VERSION,
+            seed: old.seed,
+            generated_chunks: old.generated_chunks,
+            modified_tiles: old.modified_tiles.into_iter().map(Tile::from).collect(),
+            structures: old.structures.into_iter().map(Structure::from).collect(),
+            builder: old.builder.into(),
+            next_enemy_id: old.next_enemy_id,
+            enemies: old.enemies.into_iter().map(EnemyRecord::from).collect(),
+        }
+    }
+}
+
+/// Up to version 3 tiles had no resource nodes.
+#[derive(Deserialize)]
+struct TileV1 {
+    x: i32,
+    y: i32,
+    biome: Biome,
+    variant: u32,
+}
+
+impl From<TileV1> for Tile {
+    fn from(old: TileV1) -> Tile {
+        Tile {
+            x: old.x,
+            y: old.y,
+            biome: old.biome,
+            variant: old.variant,
+            resource: Option::None,
+        }
+    }
+}
+
+/// Version 1 structures couldn't be rotated, and all faced east.
+#[derive(Deserialize)]
+struct StructureV1 {
+    structure_type: StructureType,
+    tile: TileCoordinate,
+}
+
+impl From<StructureV1> for Structure {
+    fn from(old: StructureV1) -> Structure {
+        Structure::new(old.structure_type, old.tile, CardinalDirection::East)
+    }
+}
+
+/// Up to version 2 the builder carried no items.
+#[derive(Deserialize)]
+struct BuilderStateV1 {
+    position: (f32, f32),
+    mode: BuilderMode,
+}
+
+impl From<BuilderStateV1> for BuilderState {
+    fn from(old: BuilderStateV1) -> BuilderState {
+        BuilderState {
+            position: old.position,
+            mode: old.mode,
+            inventory: Inventory::new(BUILDER_INVENTORY_SLOTS),
+            hp: Option::None,
+        }
+    }
+}
+
+/// Versions 3 and 4 saved the builder's items but not its health.
+#[derive(Deserialize)]
+struct BuilderStateV3 {
+    position: (f32, f32),
+    mode: BuilderMode,
+    inventory: Inventory,
+}
+
+impl From<BuilderStateV3> for BuilderState {
+    fn from(old: BuilderStateV3) -> BuilderState {
+        BuilderState {
+            position: old.position,
+            mode: old.mode,
+            inventory: old.inventory,
+            hp: Option::None,
+        }
+    }
+}
+
+/// Up to version 5 every enemy was a goblin, and waves didn't send any raiders.
+#[derive(Deserialize)]
+struct EnemyRecordV1 {
+    id: EnemyId,
+    hp: i32,
+    position: (f32, f32),
+    home: TileCoordinate,
+}
+
+impl From<EnemyRecordV1> for EnemyRecord {
+    fn from(old: EnemyRecordV1) -> EnemyRecord {
+        EnemyRecord {
+            id: old.id,
+            enemy_type: EnemyType::Goblin,
+            hp: old.hp,
+            position: old.position,
+            home: old.home,
+            raider: false,
+            entity: Option::None,
+        }
+    }
+}
+
+/// Version 6 saved raiders, but every enemy was still a goblin.
+#[derive(Deserialize)]
+struct EnemyRecordV6 {
+    id: EnemyId,
+    hp: i32,
+    position: (f32, f32),
+    home: TileCoordinate,
+    raider: bool,
+}
+
+impl From<EnemyRecordV6> for EnemyRecord {
+    fn from(old: EnemyRecordV6) -> EnemyRecord {
+        EnemyRecord {
+            id: old.id,
+            enemy_type: EnemyType::Goblin,
+            hp: old.hp,
+            position: old.position,
+            home: old.home,
+            raider: old.raider,
+            entity: Option::None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::Item;
+
+    // written before structures could be rotated
+    const VERSION_1_SAVE: &str = "(
+        version: 1,
+        seed: 42,
+        generated_chunks: [(0, 0), (-1, 0)],
+        modified_tiles: [(x: 5, y: 5, biome: Grassland, variant: 100)],
+        structures: [(structure_type: Conveyor, tile: (1, 2))],
+        builder: (position: (16, -32), mode: Construct),
+        next_enemy_id: 2,
+        enemies: [
+            (id: (0), hp: 80, position: (48, 48), home: (3, 3)),
+            (id: (1), hp: 25, position: (-64, 32), home: (-4, 2)),
+        ],
+    )";
+
+    #[test]
+    fn test_migrate_version_1() {
+        let migrated = SaveGame::from_ron(VERSION_1_SAVE).unwrap();
+        assert_eq!(SAVE_FORMAT_VERSION, migrated.version);
+        assert_eq!(
+            StructureType::Conveyor,
+            migrated.structures[0].structure_type
+        );
+        assert_eq!(CardinalDirection::East, migrated.structures[0].facing);
+        assert_eq!(BuilderMode::Construct, migrated.builder.mode);
+        assert_eq!(0, migrated.builder.inventory.stacks().count());
+        assert_eq!(25, migrated.enemies[1].hp);
+
+        // once migrated, the save is written and read back in the current format
+        let reloaded = SaveGame::from_ron(&migrated.to_ron().unwrap()).unwrap();
+        assert_eq!(SAVE_FORMAT_VERSION, reloaded.version);
+        assert_eq!(TileCoordinate(1, 2), reloaded.structures[0].tile);
+        assert_eq!(CardinalDirection::East, reloaded.structures[0].facing);
+        assert_eq!(2, reloaded.generated_chunks.len());
+        assert_eq!(100, reloaded.modified_tiles[0].variant);
+        assert_eq!(2, reloaded.next_enemy_id);
+    }
+
+    // written before the builder carried items
+    const VERSION_2_SAVE: &str = "(
+        version: 2,
+        seed: 42,
+        generated_chunks: [(0, 0)],
+        modified_tiles: [],
+        structures: [(structure_type: Conveyor, tile: (1, 2), facing: South)],
+        builder: (position: (16, -32), mode: Combat),
+        next_enemy_id: 0,
+        enemies: [],
+    )";
+
+    #[test]
+    fn test_migrate_version_2() {
+        let migrated = SaveGame::from_ron(VERSION_2_SAVE).unwrap();
+        assert_eq!(CardinalDirection::South, migrated.structures[0].facing);
+        assert_eq!(0, migrated.builder.inventory.stacks().count());
+
+        let reloaded = SaveGame::from_ron(&migrated.to_ron().unwrap()).unwrap();
+        assert_eq!(SAVE_FORMAT_VERSION, reloaded.version);
+        assert_eq!(BuilderMode::Combat, reloaded.builder.mode);
+        assert_eq!(migrated.builder.inventory, reloaded.builder.inventory);
+    }
+
+    // written before tiles had resource nodes
+    const VERSION_3_SAVE: &str = "(
+        version: 3,
+        seed: 42,
+        generated_chunks: [(0, 0)],
+        modified_tiles: [(x: 5, y: 5, biome: Desert, variant: 2)],
+        structures: [],
+        builder: (
+            position: (16, -32),
+            mode: Construct,
+            inventory: (slots: [Some((item: Stone, count: 4)), None]),
+        ),
+        next_enemy_id: 0,
+        enemies: [],
+    )";
+
+    #[test]
+    fn test_migrate_version_3() {
+        let migrated = SaveGame::from_ron(VERSION_3_SAVE).unwrap();
+        assert_eq!(Biome::Desert, migrated.modified_tiles[0].biome);
+        assert_eq!(Option::None, migrated.modified_tiles[0].resource);
+        assert_eq!(4, migrated.builder.inventory.count(Item::Stone));
+
+        let reloaded = SaveGame::from_ron(&migrated.to_ron().unwrap()).unwrap();
+        assert_eq!(SAVE_FORMAT_VERSION, reloaded.version);
+        assert_eq!(migrated.modified_tiles, reloaded.modified_tiles);
+        assert_eq!(migrated.builder.inventory, reloaded.builder.inventory);
+    }
+
+    // written before the builder could be hurt
+    const VERSION_4_SAVE: &str = "(
+        version: 4,
+        seed: 42,
+        generated_chunks: [(0, 0)],
+        modified_tiles: [
+            (x: 5, y: 5, biome: Grassland, variant: 0, resource: Some((kind: Tree, harvested: 2, depleted: false))),
+        ],
+        structures: [],
+        builder: (
+            position: (16, -32),
+            mode: Construct,
+            inventory: (slots: [Some((item: Wood, count: 2)), None]),
+        ),
+        next_enemy_id: 0,
+        enemies: [],
+    )";
+
+    #[test]
+    fn test_migrate_version_4() {
+        let migrated = SaveGame::from_ron(VERSION_4_SAVE).unwrap();
+        assert_eq!(Option::None, migrated.builder.hp);
+        assert_eq!(2, migrated.builder.inventory.count(Item::Wood));
+        assert_eq!(2, migrated.modified_tiles[0].resource.unwrap().harvested);
+
+        let reloaded = SaveGame::from_ron(&migrated.to_ron().unwrap()).unwrap();
+        assert_eq!(SAVE_FORMAT_VERSION, reloaded.version);
+        assert_eq!(Option::None, reloaded.builder.hp);
+        assert_eq!(migrated.modified_tiles, reloaded.modified_tiles);
+    }
+
+    // written before waves sent raiders
+    const VERSION_5_SAVE: &str = "(
+        version: 5,
+        seed: 42,
+        generated_chunks: [(0, 0)],
+        modified_tiles: [],
+        structures: [],
+        builder: (
+            position: (16, -32),
+            mode: Combat,
+            inventory: (slots: [None, None]),
+            hp: Some(60),
+        ),
+        next_enemy_id: 1,
+        enemies: [(id: (0), hp: 80, position: (48, 48), home: (3, 3))],
+    )";
+
+    #[test]
+    fn test_migrate_version_5() {
+        let migrated = SaveGame::from_ron(VERSION_5_SAVE).unwrap();
+        assert_eq!(Some(60), migrated.builder.hp);
+        assert!(!migrated.enemies[0].raider);
+        assert_eq!(TileCoordinate(3, 3), migrated.enemies[0].home);
+
+        let reloaded = SaveGame::from_ron(&migrated.to_ron().unwrap()).unwrap();
+        assert_eq!(SAVE_FORMAT_VERSION, reloaded.version);
+        assert_eq!(Some(60), reloaded.builder.hp);
+        assert!(!reloaded.enemies[0].raider);
+        assert_eq!(80, reloaded.enemies[0].hp);
+    }
+
+    // written before there were enemy types
+    const VERSION_6_SAVE: &str = "(
+        version: 6,
+        seed: 42,
+        generated_chunks: [(0, 0)],
+        modified_tiles: [],
+        structures: [],
+        builder: (
+            position: (16, -32),
+            mode: Combat,
+            inventory: (slots: [None, None]),
+            hp: None,
+        ),
+        next_enemy_id: 2,
+        enemies: [
+            (id: (0), hp: 80, position: (48, 48), home: (3, 3), raider: false),
+            (id: (1), hp: 100, position: (-64, 32), home: (-4, 2), raider: true),
+        ],
+    )";
+
+    #[test]
+    fn test_migrate_version_6() {
+        let migrated = SaveGame::from_ron(VERSION_6_SAVE).unwrap();
+        assert_eq!(EnemyType::Goblin, migrated.enemies[0].enemy_type);
+        assert!(migrated.enemies[1].raider);
+
+        let reloaded = SaveGame::from_ron(&migrated.to_ron().unwrap()).unwrap();
+        assert_eq!(SAVE_FORMAT_VERSION, reloaded.version);
+        assert_eq!(EnemyType::Goblin, reloaded.enemies[1].enemy_type);
+        assert!(reloaded.enemies[1].raider);
+    }
+}
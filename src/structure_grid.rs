@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::world_map::{TileCoordinate, TileRegion};
+
+/// Which structure occupies each tile. Structures may span several tiles, all of which map to
+/// the same entity.
+#[derive(Default)]
+pub struct StructureGrid {
+    occupants: HashMap<TileCoordinate, Entity>,
+    footprints: HashMap<Entity, TileRegion>,
+    // bumped on every change, so paths planned around the structures know when to re-plan
+    revision: u64,
+}
+
+impl StructureGrid {
+    /// Returns the structure on the tile, if any.
+    pub fn get(&self, tile: TileCoordinate) -> Option<Entity> {
+        self.occupants.get(&tile).cloned()
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn is_free(&self, footprint: &TileRegion) -> bool {
+        footprint
+            .coordinates()
+            .all(|tile| !self.occupants.contains_key(&tile))
+    }
+
+    /// Records the structure on every tile of its footprint. Fails without changing anything if
+    /// any of those tiles is already taken.
+    pub fn insert(&mut self, footprint: TileRegion, entity: Entity) -> Result<(), Entity> {
+        if let Some(occupant) = footprint.coordinates().find_map(|tile| self.get(tile)) {
+            return Err(occupant);
+        }
+        for tile in footprint.coordinates() {
+            self.occupants.insert(tile, entity);
+        }
+        self.footprints.insert(entity, footprint);
+        self.revision += 1;
+        Ok(())
+    }
+
+    /// Frees the tiles of the structure, returning its footprint.
+    pub fn remove(&mut self, entity: Entity) -> Option<TileRegion> {
+        let footprint = self.footprints.remove(&entity)?;
+        for tile in footprint.coordinates() {
+            self.occupants.remove(&tile);
+        }
+        self.revision += 1;
+        Some(footprint)
+    }
+
+    pub fn clear(&mut self) {
+        self.occupants.clear();
+        self.footprints.clear();
+        self.revision += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn footprint(x: i32, y: i32, width: i32, height: i32) -> TileRegion {
+        TileRegion {
+            min: TileCoordinate(x, y),
+            max: TileCoordinate(x + width - 1, y + height - 1),
+        }
+    }
+
+    #[test]
+    fn test_multi_tile_occupancy() {
+        let mut structure_grid = StructureGrid::default();
+        let producer = Entity::from_bits(1);
+        let conveyor = Entity::from_bits(2);
+
+        structure_grid
+            .insert(footprint(0, 0, 2, 2), producer)
+            .unwrap();
+        assert_eq!(Some(producer), structure_grid.get(TileCoordinate(1, 1)));
+        assert_eq!(None, structure_grid.get(TileCoordinate(2, 1)));
+
+        // overlapping a single tile of the producer is enough to be rejected
+        assert!(!structure_grid.is_free(&footprint(1, -1, 1, 2)));
+        assert_eq!(
+            Err(producer),
+            structure_grid.insert(footprint(1, -1, 1, 2), conveyor)
+        );
+        assert_eq!(None, structure_grid.get(TileCoordinate(1, -1)));
+
+        assert_eq!(Some(footprint(0, 0, 2, 2)), structure_grid.remove(producer));
+        assert!(structure_grid.is_free(&footprint(0, 0, 2, 2)));
+        structure_grid
+            .insert(footprint(1, -1, 1, 2), conveyor)
+            .unwrap();
+    }
+}
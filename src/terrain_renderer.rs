@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy::render::mesh::Indices;
+use bevy::render::pipeline::PrimitiveTopology;
+use bevy::sprite::SpriteResizeMode;
+
+use crate::asset_loader::AtlasHandles;
+use crate::biome::Biome;
+use crate::global_constants::TILE_LENGTH;
+use crate::world_map::{tile_to_position, Chunk, WorldMap};
+
+/// One material per biome atlas texture, shared by every chunk mesh.
+#[derive(Default)]
+pub struct TerrainMaterials {
+    materials: HashMap<Biome, Handle<ColorMaterial>>,
+}
+
+impl TerrainMaterials {
+    fn get_or_add(
+        &mut self,
+        biome: Biome,
+        texture: &Handle<Texture>,
+        color_materials: &mut Assets<ColorMaterial>,
+    ) -> Handle<ColorMaterial> {
+        self.materials
+            .entry(biome)
+            .or_insert_with(|| color_materials.add(ColorMaterial::texture(texture.clone())))
+            .clone()
+    }
+}
+
+/// Builds one mesh per biome present in each chunk whose tiles have changed since it was last
+/// drawn, replacing one sprite entity per tile.
+pub fn render_terrain(
+    mut commands: Commands,
+    atlas_handles: Res<AtlasHandles>,
+    texture_atlases: Res<Assets<TextureAtlas>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+    mut terrain_materials: ResMut<TerrainMaterials>,
+    mut world_map: ResMut<WorldMap>,
+) {
+    if !atlas_handles.loaded() {
+        return;
+    }
+    // chunks stay out of date, and are drawn on a later frame, until every biome atlas is ready
+    let atlases: Option<Vec<(Biome, &TextureAtlas)>> = Biome::ALL
+        .iter()
+        .map(|biome| {
+            let atlas_id = atlas_handles.get_biome_asset(*biome)?;
+            let atlas = texture_atlases.get(Handle::<TextureAtlas>::weak(atlas_id))?;
+            Some((*biome, atlas))
+        })
+        .collect();
+    let atlases = match atlases {
+        Some(atlases) => atlases,
+        None => return,
+    };
+
+    for chunk in world_map.chunks_mut() {
+        if chunk.terrain_up_to_date {
+            continue;
+        }
+
+        for entity in chunk.terrain_entities.drain(..) {
+            commands.despawn(entity);
+        }
+
+        for (biome, atlas) in atlases.iter() {
+            if let Some(mesh) = build_chunk_mesh(chunk, *biome, atlas) {
+                let material =
+                    terrain_materials.get_or_add(*biome, &atlas.texture, &mut color_materials);
+                let terrain_entity = commands
+                    .spawn(SpriteComponents {
+                        mesh: meshes.add(mesh),
+                        material,
+                        // the mesh is already in world units, so the sprite must not rescale it
+                        sprite: Sprite {
+                            size: Vec2::new(1., 1.),
+                            resize_mode: SpriteResizeMode::Manual,
+                        },
+                        transform: tile_to_position(chunk.coordinate.origin()),
+                        ..Default::default()
+                    })
+                    .current_entity()
+                    .unwrap();
+                chunk.terrain_entities.push(terrain_entity);
+            }
+        }
+
+        chunk.terrain_up_to_date = true;
+    }
+}
+
+/// Builds a quad for each of the chunk's tiles of the given biome, positioned relative to the
+/// chunk's origin tile. Returns `None` if the chunk has no such tiles.
+fn build_chunk_mesh(chunk: &Chunk, biome: Biome, atlas: &TextureAtlas) -> Option<Mesh> {
+    let origin = chunk.coordinate.origin();
+    let tile_length = TILE_LENGTH as f32;
+    let half_length = tile_length / 2.;
+
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+
+    for tile in chunk.tiles().iter().filter(|tile| tile.biome == biome) {
+        let center_x = (tile.x - origin.0) as f32 * tile_length;
+        let center_y = (tile.y - origin.1) as f32 * tile_length;
+
+        // a variant past the end of the atlas falls back to its first sprite rather than panic
+        let rect = match atlas
+            .textures
+            .get(tile.variant as usize)
+            .or_else(|| atlas.textures.first())
+        {
+            Some(rect) => rect,
+            None => continue,
+        };
+        let (min_u, min_v) = (rect.min.x() / atlas.size.x(), rect.min.y() / atlas.size.y());
+        let (max_u, max_v) = (rect.max.x() / atlas.size.x(), rect.max.y() / atlas.size.y());
+
+        let first_index = positions.len() as u32;
+        // texture v grows downward, so the bottom vertices take max_v
+        positions.push([center_x - half_length, center_y - half_length, 0.]);
+        uvs.push([min_u, max_v]);
+        positions.push([center_x + half_length, center_y - half_length, 0.]);
+        uvs.push([max_u, max_v]);
+        positions.push([center_x + half_length, center_y + half_length, 0.]);
+        uvs.push([max_u, min_v]);
+        positions.push([center_x - half_length, center_y + half_length, 0.]);
+        uvs.push([min_u, min_v]);
+        normals.extend_from_slice(&[[0., 0., 1.]; 4]);
+        indices.extend_from_slice(&[
+            first_index,
+            first_index + 1,
+            first_index + 2,
+            first_index,
+            first_index + 2,
+            first_index + 3,
+        ]);
+    }
+
+    if positions.is_empty() {
+        return None;
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions.into());
+    mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, normals.into());
+    mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, uvs.into());
+    Some(mesh)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world_generator::WorldGenerator;
+    use crate::world_map::TileCoordinate;
+
+    #[test]
+    fn test_build_chunk_mesh() {
+        let mut world_map = WorldMap::new();
+        world_map.update_loaded_chunks(TileCoordinate(0, 0), &WorldGenerator::new(0, 48., 0., 0.));
+        let chunk = world_map.chunks_mut().next().unwrap();
+        let atlas = TextureAtlas::from_grid(Handle::default(), Vec2::new(16., 16.), 4, 1);
+
+        for biome in Biome::ALL.iter() {
+            let tile_count = chunk
+                .tiles()
+                .iter()
+                .filter(|tile| tile.biome == *biome)
+                .count();
+            let mesh = match build_chunk_mesh(chunk, *biome, &atlas) {
+                Some(mesh) => mesh,
+                None => {
+                    assert_eq!(0, tile_count, "{:?}", biome);
+                    continue;
+                }
+            };
+            // a quad of four vertices and two triangles per tile
+            let positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION).unwrap();
+            let uvs = mesh.attribute(Mesh::ATTRIBUTE_UV_0).unwrap();
+            assert_eq!(4 * tile_count, positions.len(), "{:?}", biome);
+            assert_eq!(4 * tile_count, uvs.len(), "{:?}", biome);
+            match mesh.indices() {
+                Some(Indices::U32(indices)) => assert_eq!(6 * tile_count, indices.len()),
+                _ => panic!("expected 32 bit indices"),
+            }
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use bevy::prelude::*;
+use bevy_rapier3d::physics::RigidBodyHandleComponent;
+use bevy_rapier3d::rapier::dynamics::RigidBodySet;
+use bevy_rapier3d::rapier::na::{Isometry3, Vector3};
+
+use crate::asset_loader::AtlasHandles;
+use crate::builder::compute_projectile_positions;
+use crate::construction::Structure;
+use crate::data::{AssetType, Prefabs};
+use crate::enemy::Enemy;
+use crate::logistics::Conveyor;
+use crate::projectile::spawn_projectiles;
+use crate::structure_grid::StructureGrid;
+
+/// A building that shoots at the nearest enemy in range. Its range, fire rate and weapon come from
+/// the tower prefabs.
+#[derive(Default)]
+pub struct Tower {
+    // created on the first update, once the tower's fire rate is known
+    reload: Option<Timer>,
+    // shots left, for towers that use ammo
+    ammo: u32,
+}
+
+/// Reloads every tower, feeds ammo to the ones that need it from conveyors pointing into them,
+/// and fires at the nearest enemy in range.
+#[allow(clippy::too_many_arguments)]
+pub fn operate_towers(
+    mut commands: Commands,
+    time: Res<Time>,
+    atlas_handles: Res<AtlasHandles>,
+    prefabs: Res<Prefabs>,
+    rigid_body_set: Res<RigidBodySet>,
+    structure_grid: Res<StructureGrid>,
+    mut tower_query: Query<(&Structure, &mut Tower)>,
+    mut conveyor_query: Query<&mut Conveyor>,
+    enemy_query: Query<(&Enemy, &RigidBodyHandleComponent)>,
+) {
+    let arrow_id = match atlas_handles.get_asset(AssetType::Arrow) {
+        Some(arrow_id) => arrow_id,
+        None => return,
+    };
+
+    let enemy_positions: Vec<Vector3<f32>> = enemy_query
+        .iter()
+        .filter_map(|(_enemy, enemy_body_handle)| rigid_body_set.get(enemy_body_handle.handle()))
+        .map(|enemy_body| enemy_body.position().translation.vector)
+        .collect();
+
+    for (structure, mut tower) in tower_query.iter_mut() {
+        let tower_info = prefabs.tower(structure.structure_type);
+        let tower = &mut *tower;
+
+        if let Some(ammo_item) = tower_info.ammo {
+            if tower.ammo < tower_info.ammo_capacity {
+                for (input_tile, direction) in structure.footprint().border() {
+                    let conveyor_entity = match structure_grid.get(input_tile) {
+                        Some(conveyor_entity) => conveyor_entity,
+                        None => continue,
+                    };
+                    if let Ok(mut conveyor) =
+                        conveyor_query.get_component_mut::<Conveyor>(conveyor_entity)
+                    {
+                        // only belts pointing into the tower hand it their items
+                        if conveyor.direction == direction.opposite()
+                            && conveyor.output() == Some(ammo_item)
+                        {
+                            conveyor.consume_output(&mut commands);
+                            tower.ammo += 1;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        let reload = tower
+            .reload
+            .get_or_insert_with(|| Timer::from_seconds(tower_info.seconds_per_shot, false));
+        reload.tick(time.delta_seconds);
+        if !reload.finished || (tower_info.ammo.is_some() && tower.ammo == 0) {
+            continue;
+        }
+
+        let weapon = prefabs.weapon(tower_info.weapon);
+        let projectile = match weapon.projectile_type {
+            Some(projectile_type) => prefabs.projectile(projectile_type),
+            None => continue,
+        };
+
+        let center = structure.sprite_transform().translation;
+        let origin = Isometry3::translation(center.x(), center.y(), 0.);
+        let target = match nearest_target(&origin, tower_info.range, &enemy_positions) {
+            Some(target) => target,
+            None => continue,
+        };
+
+        let mut aim_vector = target - origin.translation.vector;
+        aim_vector.z = 0.;
+        let projectile_positions =
+            compute_projectile_positions(&origin, aim_vector.normalize(), &weapon, &projectile);
+        spawn_projectiles(
+            &mut commands,
+            arrow_id,
+            &prefabs,
+            &projectile,
+            projectile_positions,
+        );
+
+        reload.reset();
+        if tower_info.ammo.is_some() {
+            tower.ammo -= 1;
+        }
+    }
+}
+
+/// Returns the closest of `positions` within `range` of the origin, ignoring height.
+fn nearest_target(
+    origin: &Isometry3<f32>,
+    range: f32,
+    positions: &[Vector3<f32>],
+) -> Option<Vector3<f32>> {
+    // distances are squared to skip the sqrt
+    let distance_squared = |position: &Vector3<f32>| {
+        let offset = position - origin.translation.vector;
+        offset.x * offset.x + offset.y * offset.y
+    };
+    positions
+        .iter()
+        .filter(|position| distance_squared(position) <= range * range)
+        .min_by(|a, b| distance_squared(a).total_cmp(&distance_squared(b)))
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nearest_target() {
+        let origin = Isometry3::translation(10., 10., 0.);
+        let positions = vec![
+            Vector3::new(10., 60., 0.),
+            Vector3::new(40., 10., 0.),
+            Vector3::new(-200., 10., 0.),
+        ];
+
+        assert_eq!(
+            Some(Vector3::new(40., 10., 0.)),
+            nearest_target(&origin, 100., &positions)
+        );
+        assert_eq!(None, nearest_target(&origin, 20., &positions));
+        assert_eq!(None, nearest_target(&origin, 100., &[]));
+    }
+}
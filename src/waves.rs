@@ -0,0 +1,217 @@
+use bevy::prelude::*;
+use bevy_rapier3d::physics::RigidBodyHandleComponent;
+use bevy_rapier3d::rapier::dynamics::RigidBodySet;
+
+use crate::asset_loader::AtlasHandles;
+use crate::builder::Builder;
+use crate::construction::builder_tile;
+use crate::data::Prefabs;
+use crate::display::DisplayConfig;
+use crate::enemy;
+use crate::enemy_registry::EnemyRegistry;
+use crate::pathfinding::is_passable;
+use crate::structure_grid::StructureGrid;
+use crate::world_map::{TileCoordinate, TileRegion, WorldMap};
+use crate::world_renderer::{WORLD_MAP_RENDER_HEIGHT, WORLD_MAP_RENDER_WIDTH};
+
+const COUNTDOWN_FONT: &str = "font/square.ttf";
+const COUNTDOWN_FONT_SIZE: f32 = 22.;
+
+// in tiles around the spot a wave arrives at
+const SPAWN_SPREAD: i32 = 2;
+// in tiles between the edge of the screen and the spots waves arrive at, so even the spread out
+// enemies walk in from out of sight
+const SPAWN_MARGIN: i32 = SPAWN_SPREAD + 1;
+
+fn countdown_color() -> Color {
+    Color::rgb(1., 0.9, 0.3)
+}
+
+/// Sends escalating waves of enemies at the builder from just off screen, on the schedule of the
+/// wave prefab, and counts down to the next one at the top of the screen.
+pub struct WavePlugin;
+
+impl Plugin for WavePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<WaveDirector>()
+            .add_startup_system(spawn_wave_countdown.system())
+            .add_system(direct_waves.system())
+            .add_system(update_wave_countdown.system());
+    }
+}
+
+/// The number of the next wave and the time left until it comes.
+pub struct WaveDirector {
+    next_wave: usize,
+    timer: Timer,
+}
+
+impl FromResources for WaveDirector {
+    fn from_resources(resources: &Resources) -> Self {
+        let prefabs = resources.get::<Prefabs>().unwrap();
+        WaveDirector {
+            next_wave: 0,
+            timer: Timer::from_seconds(prefabs.waves().first_wave_seconds, false),
+        }
+    }
+}
+
+/// Marks the text counting down to the next wave.
+pub struct WaveCountdown;
+
+fn spawn_wave_countdown(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    display_config: Res<DisplayConfig>,
+) {
+    // drawn by the UI camera the hotbar spawns
+    commands
+        .spawn(TextComponents {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.),
+                    top: Val::Px(10.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text {
+                value: String::new(),
+                font: asset_server.load(COUNTDOWN_FONT),
+                style: TextStyle {
+                    font_size: COUNTDOWN_FONT_SIZE * display_config.ui_scale,
+                    color: countdown_color(),
+                },
+            },
+            ..Default::default()
+        })
+        .with(WaveCountdown);
+}
+
+/// Counts down to the next wave once the builder is in the world, and sends it at the builder
+/// from a random spot just off screen. Unless the wave says otherwise, its enemies are of the
+/// kinds the biome they arrive from spawns.
+#[allow(clippy::too_many_arguments)]
+fn direct_waves(
+    mut commands: Commands,
+    time: Res<Time>,
+    atlas_handles: Res<AtlasHandles>,
+    prefabs: Res<Prefabs>,
+    rigid_body_set: Res<RigidBodySet>,
+    world_map: Res<WorldMap>,
+    structure_grid: Res<StructureGrid>,
+    mut enemy_registry: ResMut<EnemyRegistry>,
+    mut wave_director: ResMut<WaveDirector>,
+    builder_query: Query<(&Builder, &RigidBodyHandleComponent)>,
+) {
+    let builder_tile = match builder_query.iter().next() {
+        Some((_builder, builder_body_handle)) => builder_tile(&rigid_body_set, builder_body_handle),
+        None => return,
+    };
+
+    wave_director.timer.tick(time.delta_seconds);
+    if !wave_director.timer.finished {
+        return;
+    }
+
+    let schedule = prefabs.waves();
+    let wave = schedule.wave(wave_director.next_wave);
+    let spawn_tiles = wave_spawn_tiles(builder_tile, wave.enemies as usize, |tile| {
+        is_passable(tile, &world_map, &structure_grid)
+    });
+    println!(
+        "Wave {} is coming with {} enemies",
+        wave_director.next_wave + 1,
+        spawn_tiles.len()
+    );
+    for spawn_tile in spawn_tiles {
+        let enemy_type = wave.enemy_type.unwrap_or_else(|| {
+            let biome = world_map.get_tile(spawn_tile).unwrap().biome;
+            prefabs.enemy_spawn(biome, rand::random())
+        });
+        let hp = wave.hp(prefabs.enemy(enemy_type).max_hp);
+        enemy_registry.register_raider(spawn_tile, enemy_type, hp);
+        // the spawn tiles are off screen, so the raiders are woken right away rather than when
+        // their tiles come into view
+        for enemy_record in enemy_registry.wake_at(spawn_tile) {
+            let enemy_entity =
+                enemy::spawn_enemy(&mut commands, &atlas_handles, &prefabs, &enemy_record);
+            enemy_registry.set_spawned(enemy_record.id, enemy_entity);
+        }
+    }
+
+    wave_director.next_wave += 1;
+    wave_director.timer = Timer::from_seconds(schedule.seconds_between_waves, false);
+}
+
+/// Picks the tiles a wave of `count` enemies starts on: passable tiles around a random spot on the
+/// ring just off screen from the builder. Fewer tiles are returned if not enough are free.
+fn wave_spawn_tiles(
+    builder_tile: TileCoordinate,
+    count: usize,
+    is_passable: impl Fn(TileCoordinate) -> bool,
+) -> Vec<TileCoordinate> {
+    let ring = TileRegion::around(
+        builder_tile,
+        WORLD_MAP_RENDER_WIDTH + SPAWN_MARGIN,
+        WORLD_MAP_RENDER_HEIGHT + SPAWN_MARGIN,
+    )
+    .border();
+    let (arrival_tile, _direction) = ring[(rand::random::<f32>() * ring.len() as f32) as usize];
+
+    TileRegion::around(arrival_tile, SPAWN_SPREAD, SPAWN_SPREAD)
+        .coordinates()
+        .filter(|tile| is_passable(*tile))
+        .take(count)
+        .collect()
+}
+
+fn update_wave_countdown(
+    wave_director: Res<WaveDirector>,
+    mut countdown_query: Query<(&WaveCountdown, &mut Text)>,
+) {
+    let seconds_left = (wave_director.timer.duration - wave_director.timer.elapsed).max(0.);
+    for (_countdown, mut text) in countdown_query.iter_mut() {
+        text.value = countdown_label(wave_director.next_wave, seconds_left);
+    }
+}
+
+fn countdown_label(next_wave: usize, seconds_left: f32) -> String {
+    let seconds_left = seconds_left.ceil() as u32;
+    format!(
+        "Wave {} in {}:{:02}",
+        next_wave + 1,
+        seconds_left / 60,
+        seconds_left % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wave_spawn_tiles() {
+        let builder_tile = TileCoordinate(3, -2);
+        let screen = TileRegion::around(
+            builder_tile,
+            WORLD_MAP_RENDER_WIDTH,
+            WORLD_MAP_RENDER_HEIGHT,
+        );
+
+        let spawn_tiles = wave_spawn_tiles(builder_tile, 6, |_tile| true);
+        assert_eq!(6, spawn_tiles.len());
+        assert!(spawn_tiles.iter().all(|tile| !screen.contains(*tile)));
+
+        // only the free tiles around the arrival spot are used
+        let spawn_tiles = wave_spawn_tiles(builder_tile, 6, |tile| tile.0 % 2 == 0 && tile.1 == 0);
+        assert!(spawn_tiles.len() < 6);
+    }
+
+    #[test]
+    fn test_countdown_label() {
+        assert_eq!("Wave 1 in 2:00", countdown_label(0, 120.));
+        assert_eq!("Wave 3 in 0:06", countdown_label(2, 5.2));
+    }
+}
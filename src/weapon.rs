@@ -1,11 +1,14 @@
-use crate::data;
+use crate::data::Prefabs;
 use crate::projectile::ProjectileType;
+use serde::Deserialize;
 
-#[derive(PartialEq, Eq, Copy, Clone, Hash)]
+#[derive(Deserialize, PartialEq, Eq, Copy, Clone, Hash, Debug)]
 pub enum WeaponType {
     MagicBow,
+    Crossbow,
 }
 
+#[derive(Deserialize, Clone)]
 pub struct Weapon {
     pub size: f32,
     pub projectile_type: Option<ProjectileType>,
@@ -15,24 +18,8 @@ pub struct Weapon {
 }
 
 impl Weapon {
-    pub fn new(
-        size: f32,
-        projectile_type: Option<ProjectileType>,
-        projectile_count: u32,
-        projectile_launch_speed: f32,
-        projectile_spread: f32,
-    ) -> Self {
-        Weapon {
-            size,
-            projectile_type,
-            projectile_count,
-            projectile_launch_speed,
-            projectile_spread,
-        }
-    }
-
-    pub fn magic_bow() -> Weapon {
-        data::get_weapon_info(WeaponType::MagicBow)
+    pub fn magic_bow(prefabs: &Prefabs) -> Weapon {
+        prefabs.weapon(WeaponType::MagicBow)
     }
 
     pub fn spread_range(&self) -> std::ops::Range<i32> {
@@ -0,0 +1,320 @@
+use std::fs;
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::biome::Biome;
+use crate::data;
+use crate::resource_node::ResourceKind;
+
+pub const WORLD_CONFIG_PATH: &str = "config/world.ron";
+
+const DEFAULT_BIOME_SCALE: f32 = 48.;
+const DEFAULT_ENEMY_DENSITY: f32 = 0.001;
+const DEFAULT_RESOURCE_DENSITY: f32 = 0.03;
+const NOISE_OCTAVES: u32 = 4;
+
+// salts keep the noise layers independent of each other for the same world seed
+const TEMPERATURE_SALT: u64 = 0x7465_6d70;
+const MOISTURE_SALT: u64 = 0x6d6f_6973;
+const ENEMY_SALT: u64 = 0x656e_656d;
+const ENEMY_TYPE_SALT: u64 = 0x6b69_6e64;
+const VARIANT_SALT: u64 = 0x7661_7269;
+const RESOURCE_SALT: u64 = 0x7265_736f;
+
+/// World generation settings read from `config/world.ron`.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct WorldConfig {
+    /// Fixed seed to share a map; a random seed is chosen when absent.
+    pub seed: Option<u64>,
+    /// Rough size of a biome region, in tiles.
+    pub biome_scale: f32,
+    pub enemy_density: f32,
+    /// Share of tiles holding a resource node.
+    pub resource_density: f32,
+}
+
+impl Default for WorldConfig {
+    fn default() -> Self {
+        WorldConfig {
+            seed: Option::None,
+            biome_scale: DEFAULT_BIOME_SCALE,
+            enemy_density: DEFAULT_ENEMY_DENSITY,
+            resource_density: DEFAULT_RESOURCE_DENSITY,
+        }
+    }
+}
+
+impl WorldConfig {
+    /// Reads the world config, falling back to defaults if the file is missing or invalid.
+    pub fn load<P: AsRef<Path>>(path: P) -> WorldConfig {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(source) => ron::from_str(&source).unwrap_or_else(|err| {
+                eprintln!(
+                    "Warning: invalid {}, using default world settings: {}",
+                    path.display(),
+                    err
+                );
+                WorldConfig::default()
+            }),
+            Err(err) => {
+                eprintln!(
+                    "Warning: could not read {}, using default world settings: {}",
+                    path.display(),
+                    err
+                );
+                WorldConfig::default()
+            }
+        }
+    }
+}
+
+/// Deterministic terrain generator: the same seed always produces the same world.
+///
+/// Tile coordinates are relative to the spawn tile, so the generator does not depend on the map
+/// dimensions.
+pub struct WorldGenerator {
+    pub seed: u64,
+    pub biome_scale: f32,
+    pub enemy_density: f32,
+    pub resource_density: f32,
+}
+
+impl WorldGenerator {
+    pub fn new(seed: u64, biome_scale: f32, enemy_density: f32, resource_density: f32) -> Self {
+        WorldGenerator {
+            seed,
+            biome_scale,
+            enemy_density,
+            resource_density,
+        }
+    }
+
+    pub fn biome_at(&self, x: i32, y: i32) -> Biome {
+        let sample_x = x as f32 / self.biome_scale;
+        let sample_y = y as f32 / self.biome_scale;
+        let temperature = fractal_noise(self.seed ^ TEMPERATURE_SALT, sample_x, sample_y);
+        let moisture = fractal_noise(self.seed ^ MOISTURE_SALT, sample_x, sample_y);
+
+        if moisture >= 0.5 {
+            Biome::Grassland
+        } else if temperature >= 0.5 {
+            Biome::Desert
+        } else {
+            Biome::Rockland
+        }
+    }
+
+    pub fn contains_enemy(&self, x: i32, y: i32) -> bool {
+        unit_hash(self.seed ^ ENEMY_SALT, x, y) < self.enemy_density
+    }
+
+    /// Roll in `0.0..1.0` for picking the kind of enemy starting on the tile from its biome's spawn
+    /// table, so the same seed always spawns the same enemies.
+    pub fn enemy_roll(&self, x: i32, y: i32) -> f32 {
+        unit_hash(self.seed ^ ENEMY_TYPE_SALT, x, y)
+    }
+
+    /// Returns the kind of resource node the tile starts with, if any.
+    pub fn resource_at(&self, x: i32, y: i32, biome: Biome) -> Option<ResourceKind> {
+        if unit_hash(self.seed ^ RESOURCE_SALT, x, y) < self.resource_density {
+            Some(ResourceKind::of_biome(biome))
+        } else {
+            Option::None
+        }
+    }
+
+    /// Picks the tile's sprite variant from the biome's weighted variant table.
+    pub fn variant_at(&self, x: i32, y: i32, biome: Biome) -> u32 {
+        weighted_choice(
+            &data::get_biome_variant_weights(biome),
+            unit_hash(self.seed ^ VARIANT_SALT, x, y),
+        )
+    }
+}
+
+/// Returns the index whose cumulative weight range contains `roll`, a value in `0.0..1.0`.
+pub fn weighted_choice(weights: &[u32], roll: f32) -> u32 {
+    let total: u32 = weights.iter().sum();
+    let target = (roll * total as f32) as u32;
+    let mut cumulative = 0;
+    for (index, weight) in weights.iter().enumerate() {
+        cumulative += weight;
+        if target < cumulative {
+            return index as u32;
+        }
+    }
+    (weights.len() - 1) as u32
+}
+
+impl FromResources for WorldGenerator {
+    fn from_resources(_resources: &Resources) -> Self {
+        let world_config = WorldConfig::load(WORLD_CONFIG_PATH);
+        let seed = world_config.seed.unwrap_or_else(rand::random);
+        println!("World seed: {}", seed);
+        WorldGenerator::new(
+            seed,
+            world_config.biome_scale,
+            world_config.enemy_density,
+            world_config.resource_density,
+        )
+    }
+}
+
+/// Sums octaves of value noise, normalized to `0.0..1.0`.
+fn fractal_noise(seed: u64, x: f32, y: f32) -> f32 {
+    let mut total = 0.;
+    let mut amplitude = 1.;
+    let mut frequency = 1.;
+    let mut total_amplitude = 0.;
+    for octave in 0..NOISE_OCTAVES {
+        total += value_noise(
+            seed.wrapping_add(octave as u64),
+            x * frequency,
+            y * frequency,
+        ) * amplitude;
+        total_amplitude += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.;
+    }
+    total / total_amplitude
+}
+
+/// Smoothly interpolates between random values placed on the integer lattice.
+fn value_noise(seed: u64, x: f32, y: f32) -> f32 {
+    let x_floor = x.floor();
+    let y_floor = y.floor();
+    let (lattice_x, lattice_y) = (x_floor as i32, y_floor as i32);
+    let tx = smoothstep(x - x_floor);
+    let ty = smoothstep(y - y_floor);
+
+    let bottom = lerp(
+        unit_hash(seed, lattice_x, lattice_y),
+        unit_hash(seed, lattice_x + 1, lattice_y),
+        tx,
+    );
+    let top = lerp(
+        unit_hash(seed, lattice_x, lattice_y + 1),
+        unit_hash(seed, lattice_x + 1, lattice_y + 1),
+        tx,
+    );
+    lerp(bottom, top, ty)
+}
+
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3. - 2. * t)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// Hashes a lattice point to a value in `0.0..1.0`.
+fn unit_hash(seed: u64, x: i32, y: i32) -> f32 {
+    (hash(seed, x, y) >> 40) as f32 / (1u64 << 24) as f32
+}
+
+// splitmix64 finalizer over the mixed coordinates
+fn hash(seed: u64, x: i32, y: i32) -> u64 {
+    let mut h = seed
+        ^ (x as u32 as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        ^ (y as u32 as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f);
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^ (h >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate(generator: &WorldGenerator, size: i32) -> Vec<Biome> {
+        let mut biomes = Vec::new();
+        for y in -size..size {
+            for x in -size..size {
+                biomes.push(generator.biome_at(x, y));
+            }
+        }
+        biomes
+    }
+
+    #[test]
+    fn test_same_seed_same_world() {
+        let first = WorldGenerator::new(
+            42,
+            DEFAULT_BIOME_SCALE,
+            DEFAULT_ENEMY_DENSITY,
+            DEFAULT_RESOURCE_DENSITY,
+        );
+        let second = WorldGenerator::new(
+            42,
+            DEFAULT_BIOME_SCALE,
+            DEFAULT_ENEMY_DENSITY,
+            DEFAULT_RESOURCE_DENSITY,
+        );
+        assert_eq!(generate(&first, 50), generate(&second, 50));
+
+        let other = WorldGenerator::new(
+            43,
+            DEFAULT_BIOME_SCALE,
+            DEFAULT_ENEMY_DENSITY,
+            DEFAULT_RESOURCE_DENSITY,
+        );
+        assert_ne!(generate(&first, 50), generate(&other, 50));
+    }
+
+    #[test]
+    fn test_all_biomes_generated() {
+        let generator = WorldGenerator::new(
+            7,
+            DEFAULT_BIOME_SCALE,
+            DEFAULT_ENEMY_DENSITY,
+            DEFAULT_RESOURCE_DENSITY,
+        );
+        let biomes = generate(&generator, 150);
+        assert!(biomes.contains(&Biome::Grassland));
+        assert!(biomes.contains(&Biome::Desert));
+        assert!(biomes.contains(&Biome::Rockland));
+    }
+
+    #[test]
+    fn test_resources_match_biome() {
+        let generator = WorldGenerator::new(5, DEFAULT_BIOME_SCALE, 0., 0.2);
+        let mut resource_count = 0;
+        for y in -40..40 {
+            for x in -40..40 {
+                if let Some(resource_kind) = generator.resource_at(x, y, generator.biome_at(x, y)) {
+                    assert_eq!(
+                        ResourceKind::of_biome(generator.biome_at(x, y)),
+                        resource_kind
+                    );
+                    resource_count += 1;
+                }
+            }
+        }
+        assert!(resource_count > 0);
+
+        let barren = WorldGenerator::new(5, DEFAULT_BIOME_SCALE, 0., 0.);
+        assert_eq!(None, barren.resource_at(3, 3, Biome::Grassland));
+    }
+
+    #[test]
+    fn test_weighted_choice() {
+        let weights = [3, 0, 1];
+        assert_eq!(0, weighted_choice(&weights, 0.));
+        assert_eq!(0, weighted_choice(&weights, 0.74));
+        assert_eq!(2, weighted_choice(&weights, 0.75));
+        assert_eq!(2, weighted_choice(&weights, 0.999));
+    }
+
+    #[test]
+    fn test_noise_range() {
+        for i in 0..1000 {
+            let value = fractal_noise(1, i as f32 * 0.37, i as f32 * -0.21);
+            assert!((0. ..=1.).contains(&value));
+        }
+    }
+}
@@ -1,136 +1,504 @@
-use crate::asset_loader::AtlasHandles;
+use std::collections::HashMap;
+
+use crate::animation::CardinalDirection;
 use crate::biome::Biome;
 use crate::global_constants::TILE_LENGTH;
+use crate::resource_node::ResourceNode;
+use crate::world_generator::WorldGenerator;
 use crate::world_renderer::{WORLD_MAP_RENDER_HEIGHT, WORLD_MAP_RENDER_WIDTH};
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-pub const WORLD_MAP_WIDTH: usize = 300;
-pub const WORLD_MAP_HEIGHT: usize = 300;
+// in number of tiles per side
+pub const CHUNK_SIZE: i32 = 32;
 
-pub struct TileCoordinate(pub usize, pub usize);
+// in number of chunks around the camera's chunk
+pub const CHUNK_LOAD_RADIUS: i32 = 1;
+pub const CHUNK_UNLOAD_RADIUS: i32 = 3;
 
-pub struct WorldMap {
-    tiles: Vec<Tile>,
+pub const ALL_DIRECTIONS: [CardinalDirection; 4] = [
+    CardinalDirection::North,
+    CardinalDirection::East,
+    CardinalDirection::South,
+    CardinalDirection::West,
+];
+
+/// Signed tile coordinate; tile (0, 0) is centered on the world origin.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Copy, Clone, Hash, Debug)]
+pub struct TileCoordinate(pub i32, pub i32);
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Copy, Clone, Hash, Debug)]
+pub struct ChunkCoordinate(pub i32, pub i32);
 
-    // in number of tiles
-    width: usize,
-    height: usize,
+/// Rectangle of tiles, inclusive on both corners.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct TileRegion {
+    pub min: TileCoordinate,
+    pub max: TileCoordinate,
 }
 
-#[derive(Debug)]
-pub struct Tile {
-    pub x: usize,
-    pub y: usize,
-    pub biome: Biome,
-    pub rendered_entity: Option<Entity>,
-    pub contains_enemy: bool,
+impl TileCoordinate {
+    pub fn chunk(&self) -> ChunkCoordinate {
+        ChunkCoordinate(self.0.div_euclid(CHUNK_SIZE), self.1.div_euclid(CHUNK_SIZE))
+    }
+
+    /// Returns the adjacent tile in the given direction; north is towards positive y.
+    pub fn neighbour(&self, direction: CardinalDirection) -> TileCoordinate {
+        match direction {
+            CardinalDirection::North => TileCoordinate(self.0, self.1 + 1),
+            CardinalDirection::South => TileCoordinate(self.0, self.1 - 1),
+            CardinalDirection::West => TileCoordinate(self.0 - 1, self.1),
+            CardinalDirection::East => TileCoordinate(self.0 + 1, self.1),
+        }
+    }
+
+    fn index_in_chunk(&self) -> usize {
+        (self.1.rem_euclid(CHUNK_SIZE) * CHUNK_SIZE + self.0.rem_euclid(CHUNK_SIZE)) as usize
+    }
 }
 
-impl WorldMap {
-    fn new(width: usize, height: usize) -> WorldMap {
-        let mut tiles: Vec<Tile> = Vec::with_capacity(width * height);
-        for y in 0..width {
-            for x in 0..height {
-                tiles.push(Tile::new(x, y));
+impl ChunkCoordinate {
+    /// Tile coordinate of the chunk's bottom left corner.
+    pub fn origin(&self) -> TileCoordinate {
+        TileCoordinate(self.0 * CHUNK_SIZE, self.1 * CHUNK_SIZE)
+    }
+
+    /// Chebyshev distance in chunks.
+    pub fn distance(&self, other: &ChunkCoordinate) -> i32 {
+        (self.0 - other.0).abs().max((self.1 - other.1).abs())
+    }
+}
+
+impl TileRegion {
+    pub fn around(center: TileCoordinate, half_width: i32, half_height: i32) -> Self {
+        TileRegion {
+            min: TileCoordinate(center.0 - half_width, center.1 - half_height),
+            max: TileCoordinate(center.0 + half_width, center.1 + half_height),
+        }
+    }
+
+    pub fn contains(&self, coordinate: TileCoordinate) -> bool {
+        coordinate.0 >= self.min.0
+            && coordinate.0 <= self.max.0
+            && coordinate.1 >= self.min.1
+            && coordinate.1 <= self.max.1
+    }
+
+    pub fn expanded(&self, margin: i32) -> TileRegion {
+        TileRegion {
+            min: TileCoordinate(self.min.0 - margin, self.min.1 - margin),
+            max: TileCoordinate(self.max.0 + margin, self.max.1 + margin),
+        }
+    }
+
+    fn intersects(&self, other: &TileRegion) -> bool {
+        self.min.0 <= other.max.0
+            && self.max.0 >= other.min.0
+            && self.min.1 <= other.max.1
+            && self.max.1 >= other.min.1
+    }
+
+    /// Splits the part of this region outside of `other` into at most four strips.
+    pub fn difference(&self, other: &TileRegion) -> Vec<TileRegion> {
+        if !self.intersects(other) {
+            return vec![*self];
+        }
+
+        let mut strips = Vec::with_capacity(4);
+        if self.min.0 < other.min.0 {
+            strips.push(TileRegion {
+                min: self.min,
+                max: TileCoordinate(other.min.0 - 1, self.max.1),
+            });
+        }
+        if self.max.0 > other.max.0 {
+            strips.push(TileRegion {
+                min: TileCoordinate(other.max.0 + 1, self.min.1),
+                max: self.max,
+            });
+        }
+
+        // the top and bottom strips only span the columns shared with `other`
+        let shared_min_x = self.min.0.max(other.min.0);
+        let shared_max_x = self.max.0.min(other.max.0);
+        if self.min.1 < other.min.1 {
+            strips.push(TileRegion {
+                min: TileCoordinate(shared_min_x, self.min.1),
+                max: TileCoordinate(shared_max_x, other.min.1 - 1),
+            });
+        }
+        if self.max.1 > other.max.1 {
+            strips.push(TileRegion {
+                min: TileCoordinate(shared_min_x, other.max.1 + 1),
+                max: TileCoordinate(shared_max_x, self.max.1),
+            });
+        }
+        strips
+    }
+
+    pub fn coordinates(&self) -> impl Iterator<Item = TileCoordinate> {
+        let (min, max) = (self.min, self.max);
+        (min.1..=max.1).flat_map(move |y| (min.0..=max.0).map(move |x| TileCoordinate(x, y)))
+    }
+
+    /// Tiles just outside the region, each with the direction leading out of the region.
+    pub fn border(&self) -> Vec<(TileCoordinate, CardinalDirection)> {
+        let mut border = Vec::new();
+        for tile in self.coordinates() {
+            for direction in ALL_DIRECTIONS.iter() {
+                let neighbour = tile.neighbour(*direction);
+                if !self.contains(neighbour) {
+                    border.push((neighbour, *direction));
+                }
+            }
+        }
+        border
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Chunk {
+    pub coordinate: ChunkCoordinate,
+    tiles: Vec<Tile>,
+
+    // entities holding the chunk's terrain meshes
+    #[serde(skip)]
+    pub terrain_entities: Vec<Entity>,
+    #[serde(skip)]
+    pub terrain_up_to_date: bool,
+}
+
+impl Chunk {
+    /// Generates the chunk's tiles, adding the tiles where enemies start to `enemy_spawns`.
+    fn generate(
+        coordinate: ChunkCoordinate,
+        world_generator: &WorldGenerator,
+        enemy_spawns: &mut Vec<TileCoordinate>,
+    ) -> Chunk {
+        let origin = coordinate.origin();
+        let mut tiles = Vec::with_capacity((CHUNK_SIZE * CHUNK_SIZE) as usize);
+        for y in origin.1..origin.1 + CHUNK_SIZE {
+            for x in origin.0..origin.0 + CHUNK_SIZE {
+                let mut tile = Tile::new(x, y);
+                tile.biome = world_generator.biome_at(x, y);
+                tile.variant = world_generator.variant_at(x, y, tile.biome);
+                tile.resource = world_generator
+                    .resource_at(x, y, tile.biome)
+                    .map(ResourceNode::new);
+                if world_generator.contains_enemy(x, y) {
+                    enemy_spawns.push(tile.coordinate());
+                }
+                tiles.push(tile);
             }
         }
-        WorldMap {
+        Chunk {
+            coordinate,
             tiles,
-            width,
-            height,
+            terrain_entities: Vec::new(),
+            terrain_up_to_date: false,
         }
     }
 
+    pub fn tiles(&self) -> &[Tile] {
+        &self.tiles
+    }
+
+    fn modified_tiles<'a>(
+        &'a self,
+        world_generator: &'a WorldGenerator,
+    ) -> impl Iterator<Item = &'a Tile> {
+        self.tiles.iter().filter(move |tile| {
+            let biome = world_generator.biome_at(tile.x, tile.y);
+            let resource = world_generator
+                .resource_at(tile.x, tile.y, biome)
+                .map(ResourceNode::new);
+            tile.biome != biome
+                || tile.variant != world_generator.variant_at(tile.x, tile.y, biome)
+                || tile.resource != resource
+        })
+    }
+}
+
+/// An unbounded tile map, split into chunks that are generated when first needed.
+///
+/// Chunks far from the camera are serialized and dropped, and restored from their serialized
+/// form when revisited so changes to their tiles are kept.
+#[derive(Default)]
+pub struct WorldMap {
+    chunks: HashMap<ChunkCoordinate, Chunk>,
+    stored_chunks: HashMap<ChunkCoordinate, String>,
+
+    // tiles rendered as of the last call to get_tiles_for_update
+    visible_region: Option<TileRegion>,
+
+    // enemy starting tiles of newly generated chunks, not yet handed to the enemy registry
+    enemy_spawns: Vec<TileCoordinate>,
+
+    // bumped whenever tiles are loaded, unloaded or modified, so paths planned over them know
+    // when to re-plan
+    revision: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Tile {
+    pub x: i32,
+    pub y: i32,
+    pub biome: Biome,
+    // index of the sprite in the biome's atlas
+    pub variant: u32,
+    pub resource: Option<ResourceNode>,
+}
+
+impl WorldMap {
+    pub fn new() -> WorldMap {
+        WorldMap::default()
+    }
+
+    pub fn get_tile(&self, coordinate: TileCoordinate) -> Option<&Tile> {
+        self.chunks
+            .get(&coordinate.chunk())
+            .map(|chunk| &chunk.tiles[coordinate.index_in_chunk()])
+    }
+
+    /// Returns the tile for modification, which marks its chunk's terrain for rebuilding.
     #[allow(dead_code)]
-    pub fn get_tile(&self, x: usize, y: usize) -> Option<&Tile> {
-        self.tiles.get(y * self.width + x)
+    pub fn get_tile_mut(&mut self, coordinate: TileCoordinate) -> Option<&mut Tile> {
+        self.revision += 1;
+        self.chunks.get_mut(&coordinate.chunk()).map(|chunk| {
+            chunk.terrain_up_to_date = false;
+            &mut chunk.tiles[coordinate.index_in_chunk()]
+        })
     }
 
-    pub fn get_tile_mut(&mut self, x: usize, y: usize) -> Option<&mut Tile> {
-        self.tiles.get_mut(y * self.width + x)
+    /// Replaces the resource node on the tile. Unlike `get_tile_mut`, this leaves the terrain
+    /// alone, since nodes are drawn as sprites on top of it, and only counts as a change to the map
+    /// when the node is depleted or grows back, as harvesting doesn't move what paths go around.
+    pub fn set_resource_node(&mut self, coordinate: TileCoordinate, node: ResourceNode) {
+        if let Some(chunk) = self.chunks.get_mut(&coordinate.chunk()) {
+            let tile = &mut chunk.tiles[coordinate.index_in_chunk()];
+            if tile.resource.map(|old| old.depleted) != Some(node.depleted) {
+                self.revision += 1;
+            }
+            tile.resource = Some(node);
+        }
     }
 
-    pub fn center_tile(&self) -> TileCoordinate {
-        TileCoordinate(self.width / 2 as usize, self.height / 2 as usize)
+    pub fn revision(&self) -> u64 {
+        self.revision
     }
 
-    pub fn position_to_tile(&self, x: f32, y: f32) -> TileCoordinate {
-        let center = self.center_tile();
-        let x_offset = (x / TILE_LENGTH as f32) as i32;
-        let y_offset = (y / TILE_LENGTH as f32) as i32;
-        TileCoordinate(
-            (center.0 as i32 + x_offset) as usize,
-            (center.1 as i32 + y_offset) as usize,
-        )
+    pub fn chunks_mut(&mut self) -> impl Iterator<Item = &mut Chunk> {
+        self.chunks.values_mut()
     }
 
-    pub fn tile_to_position(&self, x: usize, y: usize) -> Transform {
-        tile_to_position(&self.center_tile(), x, y)
+    /// Restores the chunk from storage if it was visited before, otherwise generates it.
+    pub fn load_chunk(
+        &mut self,
+        chunk_coordinate: ChunkCoordinate,
+        world_generator: &WorldGenerator,
+    ) -> &mut Chunk {
+        if !self.chunks.contains_key(&chunk_coordinate) {
+            let chunk = self
+                .stored_chunks
+                .remove(&chunk_coordinate)
+                .and_then(|serialized| match ron::from_str(&serialized) {
+                    Ok(chunk) => Some(chunk),
+                    Err(err) => {
+                        eprintln!(
+                            "Warning: regenerating chunk {:?}, stored copy is invalid: {}",
+                            chunk_coordinate, err
+                        );
+                        None
+                    }
+                })
+                .unwrap_or_else(|| {
+                    Chunk::generate(chunk_coordinate, world_generator, &mut self.enemy_spawns)
+                });
+            self.chunks.insert(chunk_coordinate, chunk);
+            self.revision += 1;
+        }
+        self.chunks.get_mut(&chunk_coordinate).unwrap()
     }
 
-    pub fn get_tiles_for_update(
+    /// Serializes and drops the chunk, returning the entities that were rendering its terrain.
+    pub fn unload_chunk(&mut self, chunk_coordinate: ChunkCoordinate) -> Vec<Entity> {
+        if let Some(chunk) = self.chunks.remove(&chunk_coordinate) {
+            let serialized = ron::to_string(&chunk).expect("chunks are always serializable");
+            self.stored_chunks.insert(chunk_coordinate, serialized);
+            self.revision += 1;
+            chunk.terrain_entities
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Loads every chunk near the given tile and unloads the ones that are far away.
+    ///
+    /// Returns the rendered entities of unloaded chunks so they can be despawned.
+    pub fn update_loaded_chunks(
         &mut self,
-        camera_x: f32,
-        camera_y: f32,
-    ) -> (Vec<&mut Tile>, Vec<&mut Tile>) {
-        let mut tiles_to_render: Vec<&mut Tile> = Vec::new();
-        let mut tiles_to_despawn: Vec<&mut Tile> = Vec::new();
+        center: TileCoordinate,
+        world_generator: &WorldGenerator,
+    ) -> Vec<Entity> {
+        let center_chunk = center.chunk();
+        for chunk_y in -CHUNK_LOAD_RADIUS..=CHUNK_LOAD_RADIUS {
+            for chunk_x in -CHUNK_LOAD_RADIUS..=CHUNK_LOAD_RADIUS {
+                let chunk_coordinate =
+                    ChunkCoordinate(center_chunk.0 + chunk_x, center_chunk.1 + chunk_y);
+                self.load_chunk(chunk_coordinate, world_generator);
+            }
+        }
 
-        let central_tile = self.position_to_tile(camera_x, camera_y);
+        let far_chunks: Vec<ChunkCoordinate> = self
+            .chunks
+            .keys()
+            .filter(|chunk_coordinate| {
+                chunk_coordinate.distance(&center_chunk) > CHUNK_UNLOAD_RADIUS
+            })
+            .cloned()
+            .collect();
+        let mut entities_to_despawn = Vec::new();
+        for chunk_coordinate in far_chunks {
+            entities_to_despawn.extend(self.unload_chunk(chunk_coordinate));
+        }
+        entities_to_despawn
+    }
 
-        // println!("Builder coordinate: {} {}", central_tile.0, central_tile.1);
+    /// Returns the coordinates of every chunk generated so far, loaded or not.
+    pub fn generated_chunks(&self) -> Vec<ChunkCoordinate> {
+        self.chunks
+            .keys()
+            .chain(self.stored_chunks.keys())
+            .cloned()
+            .collect()
+    }
 
-        let left_x = central_tile.0 - WORLD_MAP_RENDER_WIDTH;
-        let right_x = central_tile.0 + WORLD_MAP_RENDER_WIDTH;
-        let top_y = central_tile.1 + WORLD_MAP_RENDER_HEIGHT;
-        let bot_y = central_tile.1 - WORLD_MAP_RENDER_HEIGHT;
+    /// Returns the tiles of every generated chunk that no longer match what the generator
+    /// produces for them.
+    pub fn modified_tiles(&self, world_generator: &WorldGenerator) -> Vec<Tile> {
+        let stored_chunks = self
+            .stored_chunks
+            .values()
+            .filter_map(|serialized| ron::from_str::<Chunk>(serialized).ok());
+        let mut modified_tiles = Vec::new();
+        for chunk in stored_chunks {
+            modified_tiles.extend(chunk.modified_tiles(world_generator).cloned());
+        }
+        for chunk in self.chunks.values() {
+            modified_tiles.extend(chunk.modified_tiles(world_generator).cloned());
+        }
+        modified_tiles
+    }
 
-        // println!("render in box: x{}-{}, y{}-{}", left_x, right_x, bot_y, top_y);
+    /// Rebuilds a map from saved state.
+    ///
+    /// The chunks are regenerated without enemy spawns, since their enemies are restored with the
+    /// enemy registry, and are stored until the camera comes near them.
+    pub fn restore(
+        world_generator: &WorldGenerator,
+        generated_chunks: &[ChunkCoordinate],
+        modified_tiles: Vec<Tile>,
+    ) -> WorldMap {
+        let mut world_map = WorldMap::new();
+        for chunk_coordinate in generated_chunks {
+            world_map.load_chunk(*chunk_coordinate, world_generator);
+        }
+        for tile in modified_tiles {
+            let chunk = world_map.load_chunk(tile.coordinate().chunk(), world_generator);
+            let index = tile.coordinate().index_in_chunk();
+            chunk.tiles[index] = tile;
+        }
+        world_map.enemy_spawns.clear();
 
-        for tile in self.tiles.iter_mut() {
-            let render_tile =
-                tile.x >= left_x && tile.x <= right_x && tile.y <= top_y && tile.y >= bot_y;
-            if tile.rendered_entity.is_some() {
-                if !render_tile {
-                    tiles_to_despawn.push(tile);
-                }
-            } else {
-                if render_tile {
-                    tiles_to_render.push(tile);
-                }
-            }
+        let loaded_chunks: Vec<ChunkCoordinate> = world_map.chunks.keys().cloned().collect();
+        for chunk_coordinate in loaded_chunks {
+            world_map.unload_chunk(chunk_coordinate);
         }
+        world_map
+    }
 
-        (tiles_to_render, tiles_to_despawn)
+    /// Returns the entities rendering the terrain of every loaded chunk, so they can be despawned
+    /// when the map is replaced.
+    pub fn terrain_entities(&self) -> Vec<Entity> {
+        self.chunks
+            .values()
+            .flat_map(|chunk| chunk.terrain_entities.iter().cloned())
+            .collect()
+    }
+
+    /// Returns the starting tiles of enemies in chunks generated since the last call.
+    pub fn take_enemy_spawns(&mut self) -> Vec<TileCoordinate> {
+        std::mem::take(&mut self.enemy_spawns)
     }
-}
 
-impl FromResources for WorldMap {
-    fn from_resources(_resources: &Resources) -> Self {
-        WorldMap::new(WORLD_MAP_WIDTH, WORLD_MAP_HEIGHT)
+    pub fn visible_region(&self) -> Option<TileRegion> {
+        self.visible_region
+    }
+
+    /// Returns the tiles that entered and left the visible region since the last call.
+    ///
+    /// Only the strips between the previous and current region are visited, so this is free
+    /// while the camera stays within the same tile.
+    pub fn get_tiles_for_update(
+        &mut self,
+        camera_x: f32,
+        camera_y: f32,
+    ) -> (Vec<TileCoordinate>, Vec<TileCoordinate>) {
+        let central_tile = position_to_tile(camera_x, camera_y);
+        let visible_region = TileRegion::around(
+            central_tile,
+            WORLD_MAP_RENDER_WIDTH,
+            WORLD_MAP_RENDER_HEIGHT,
+        );
+
+        match self.visible_region.replace(visible_region) {
+            Some(previous_region) if previous_region == visible_region => (Vec::new(), Vec::new()),
+            Some(previous_region) => (
+                visible_region
+                    .difference(&previous_region)
+                    .iter()
+                    .flat_map(|strip| strip.coordinates())
+                    .collect(),
+                previous_region
+                    .difference(&visible_region)
+                    .iter()
+                    .flat_map(|strip| strip.coordinates())
+                    .collect(),
+            ),
+            None => (visible_region.coordinates().collect(), Vec::new()),
+        }
     }
 }
 
 impl Tile {
-    fn new(x: usize, y: usize) -> Tile {
+    fn new(x: i32, y: i32) -> Tile {
         Tile {
             x,
             y,
             biome: Biome::Grassland,
-            rendered_entity: Option::None,
-            contains_enemy: false,
+            variant: 0,
+            resource: Option::None,
         }
     }
 
-    pub fn get_biome_handle(&self, atlas_handles: &AtlasHandles) -> Handle<TextureAtlas> {
-        Handle::weak(atlas_handles.get_biome_asset(self.biome).unwrap())
+    pub fn coordinate(&self) -> TileCoordinate {
+        TileCoordinate(self.x, self.y)
     }
 }
 
-pub fn tile_to_position(center_tile: &TileCoordinate, x: usize, y: usize) -> Transform {
+/// Returns the tile whose sprite covers the given world position.
+pub fn position_to_tile(x: f32, y: f32) -> TileCoordinate {
+    let tile_length = TILE_LENGTH as f32;
+    TileCoordinate(
+        (x / tile_length + 0.5).floor() as i32,
+        (y / tile_length + 0.5).floor() as i32,
+    )
+}
+
+pub fn tile_to_position(coordinate: TileCoordinate) -> Transform {
     Transform::from_translation(Vec3::new(
-        ((x as i32 - center_tile.0 as i32) * TILE_LENGTH as i32) as f32,
-        ((y as i32 - center_tile.1 as i32) * TILE_LENGTH as i32) as f32,
+        (coordinate.0 * TILE_LENGTH as i32) as f32,
+        (coordinate.1 * TILE_LENGTH as i32) as f32,
         0.,
     ))
 }
@@ -138,12 +506,190 @@ pub fn tile_to_position(center_tile: &TileCoordinate, x: usize, y: usize) -> Tra
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashSet;
+
+    fn test_generator() -> WorldGenerator {
+        WorldGenerator::new(0, 48., 0., 0.)
+    }
 
     #[test]
     fn test_get_tile() {
-        let wm = WorldMap::new(50, 50);
-        let tile = wm.get_tile(12, 15).unwrap();
+        let mut wm = WorldMap::new();
+        let world_generator = test_generator();
+        wm.update_loaded_chunks(TileCoordinate(0, 0), &world_generator);
+
+        let tile = wm.get_tile(TileCoordinate(12, 15)).unwrap();
         assert_eq!(12, tile.x);
         assert_eq!(15, tile.y);
+
+        // in the chunk diagonally below and left of the origin one
+        let tile = wm.get_tile(TileCoordinate(-1, -32)).unwrap();
+        assert_eq!(-1, tile.x);
+        assert_eq!(-32, tile.y);
+        // two chunks away, past the load radius
+        assert!(wm.get_tile(TileCoordinate(-1, -33)).is_none());
+
+        assert!(wm.get_tile(TileCoordinate(500, 0)).is_none());
+    }
+
+    #[test]
+    fn test_chunk_coordinates() {
+        assert_eq!(ChunkCoordinate(0, 0), TileCoordinate(0, 31).chunk());
+        assert_eq!(ChunkCoordinate(-1, 1), TileCoordinate(-1, 32).chunk());
+        assert_eq!(ChunkCoordinate(-2, -1), TileCoordinate(-33, -32).chunk());
+    }
+
+    #[test]
+    fn test_position_to_tile() {
+        assert_eq!(TileCoordinate(0, 0), position_to_tile(7.9, -8.));
+        assert_eq!(TileCoordinate(-1, 1), position_to_tile(-8.1, 8.));
+        assert_eq!(
+            TileCoordinate(-20, 3),
+            position_to_tile(
+                tile_to_position(TileCoordinate(-20, 3)).translation.x(),
+                tile_to_position(TileCoordinate(-20, 3)).translation.y()
+            )
+        );
+    }
+
+    #[test]
+    fn test_unloaded_chunks_keep_changes() {
+        let mut wm = WorldMap::new();
+        let world_generator = test_generator();
+        wm.update_loaded_chunks(TileCoordinate(0, 0), &world_generator);
+        // a variant generation never produces
+        wm.get_tile_mut(TileCoordinate(3, 4)).unwrap().variant = 100;
+
+        wm.update_loaded_chunks(TileCoordinate(1000, 0), &world_generator);
+        assert!(!wm.chunks.contains_key(&ChunkCoordinate(0, 0)));
+        assert!(wm.get_tile(TileCoordinate(3, 4)).is_none());
+
+        wm.update_loaded_chunks(TileCoordinate(0, 0), &world_generator);
+        assert_eq!(100, wm.get_tile(TileCoordinate(3, 4)).unwrap().variant);
+    }
+
+    #[test]
+    fn test_region_difference() {
+        let region = TileRegion::around(TileCoordinate(0, 0), 2, 2);
+        let moved = TileRegion::around(TileCoordinate(1, -1), 2, 2);
+
+        let entering: Vec<TileCoordinate> = moved
+            .difference(&region)
+            .iter()
+            .flat_map(|strip| strip.coordinates())
+            .collect();
+        // one new column of 5 tiles and one new row of 4 tiles
+        assert_eq!(9, entering.len());
+        for coordinate in entering {
+            assert!(moved.contains(coordinate));
+            assert!(!region.contains(coordinate));
+        }
+
+        let far_away = TileRegion::around(TileCoordinate(100, 0), 2, 2);
+        assert_eq!(vec![far_away], far_away.difference(&region));
+        assert!(region.difference(&region).is_empty());
+    }
+
+    #[test]
+    fn test_region_border() {
+        let region = TileRegion {
+            min: TileCoordinate(0, 0),
+            max: TileCoordinate(1, 1),
+        };
+        let border = region.border();
+        assert_eq!(8, border.len());
+        assert!(border.contains(&(TileCoordinate(2, 1), CardinalDirection::East)));
+        assert!(border.contains(&(TileCoordinate(0, -1), CardinalDirection::South)));
+        assert!(!border
+            .iter()
+            .any(|(tile, _direction)| region.contains(*tile)));
+    }
+
+    #[test]
+    fn test_get_tiles_for_update() {
+        let mut wm = WorldMap::new();
+        let (rendered, despawned) = wm.get_tiles_for_update(-20., 300.);
+        assert_eq!(
+            ((2 * WORLD_MAP_RENDER_WIDTH + 1) * (2 * WORLD_MAP_RENDER_HEIGHT + 1)) as usize,
+            rendered.len()
+        );
+        assert!(despawned.is_empty());
+
+        // moving within the same tile changes nothing
+        let (rendered, despawned) = wm.get_tiles_for_update(-21., 301.);
+        assert!(rendered.is_empty() && despawned.is_empty());
+
+        let (rendered, despawned) = wm.get_tiles_for_update(-20. + TILE_LENGTH as f32, 300.);
+        assert_eq!((2 * WORLD_MAP_RENDER_HEIGHT + 1) as usize, rendered.len());
+        assert_eq!((2 * WORLD_MAP_RENDER_HEIGHT + 1) as usize, despawned.len());
+    }
+
+    /// The scan previously done by `get_tiles_for_update`, kept as a baseline for the benchmark.
+    fn full_scan_for_update(
+        wm: &WorldMap,
+        rendered: &mut HashSet<TileCoordinate>,
+        camera_x: f32,
+        camera_y: f32,
+    ) -> usize {
+        let visible_region = TileRegion::around(
+            position_to_tile(camera_x, camera_y),
+            WORLD_MAP_RENDER_WIDTH,
+            WORLD_MAP_RENDER_HEIGHT,
+        );
+        let mut changed = 0;
+        for tile in wm.chunks.values().flat_map(|chunk| chunk.tiles.iter()) {
+            let coordinate = tile.coordinate();
+            let render_tile = visible_region.contains(coordinate);
+            if render_tile != rendered.contains(&coordinate) {
+                if render_tile {
+                    rendered.insert(coordinate);
+                } else {
+                    rendered.remove(&coordinate);
+                }
+                changed += 1;
+            }
+        }
+        changed
+    }
+
+    /// Compares both update strategies over a 1000x1000 tile map.
+    ///
+    /// Run with `cargo test --release bench_ -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_get_tiles_for_update() {
+        let mut wm = WorldMap::new();
+        let world_generator = test_generator();
+        // rounded up to whole chunks, so at least 1000 tiles per side
+        let chunk_radius = (1000 + CHUNK_SIZE - 1) / CHUNK_SIZE / 2;
+        for chunk_y in -chunk_radius..chunk_radius {
+            for chunk_x in -chunk_radius..chunk_radius {
+                wm.load_chunk(ChunkCoordinate(chunk_x, chunk_y), &world_generator);
+            }
+        }
+
+        // walk diagonally at one pixel per frame
+        let frames = 1000;
+        let camera_path: Vec<(f32, f32)> = (0..frames).map(|i| (i as f32, i as f32)).collect();
+
+        let mut rendered = HashSet::new();
+        let start = std::time::Instant::now();
+        for (camera_x, camera_y) in camera_path.iter() {
+            full_scan_for_update(&wm, &mut rendered, *camera_x, *camera_y);
+        }
+        let full_scan_duration = start.elapsed();
+
+        let start = std::time::Instant::now();
+        for (camera_x, camera_y) in camera_path.iter() {
+            wm.get_tiles_for_update(*camera_x, *camera_y);
+        }
+        let incremental_duration = start.elapsed();
+
+        println!(
+            "full scan: {:?}/frame, incremental: {:?}/frame",
+            full_scan_duration / frames,
+            incremental_duration / frames
+        );
+        assert!(incremental_duration < full_scan_duration);
     }
 }
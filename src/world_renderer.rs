@@ -2,30 +2,46 @@ use bevy::prelude::*;
 
 use crate::animation::{AnimationBundle, UnitType};
 use crate::asset_loader::AtlasHandles;
-use crate::biome::Biome;
+use crate::builder;
 use crate::builder::Builder;
-use crate::data;
-use crate::data::AssetType;
-use crate::enemy::Enemy;
+use crate::data::{AssetType, Prefabs};
+use crate::display::WorldCamera;
+use crate::enemy;
+use crate::enemy_registry::{update_enemy_records, EnemyRegistry};
 use crate::global_constants::UNIT_Z;
+use crate::health::Health;
+use crate::resource_node::{
+    harvest_resources, hide_resource_node, regrow_resource_nodes, show_resource_node, HarvestState,
+    ResourceNodes,
+};
+use crate::terrain_renderer::{render_terrain, TerrainMaterials};
 use crate::weapon::Weapon;
-use crate::world_map::{tile_to_position, WorldMap};
-use bevy::render::camera::Camera;
+use crate::world_generator::WorldGenerator;
+use crate::world_map::{position_to_tile, tile_to_position, TileCoordinate, WorldMap};
 use bevy_rapier3d::physics::RapierConfiguration;
 use bevy_rapier3d::rapier::dynamics::RigidBodyBuilder;
 use bevy_rapier3d::rapier::na::Vector;
 
-pub const WORLD_MAP_RENDER_WIDTH: usize = 13;
-pub const WORLD_MAP_RENDER_HEIGHT: usize = 10;
-pub const ENEMY_DENSITY: f32 = 0.001;
+pub const WORLD_MAP_RENDER_WIDTH: i32 = 13;
+pub const WORLD_MAP_RENDER_HEIGHT: i32 = 10;
 
 pub struct MapGeneratorPlugin;
 
 impl Plugin for MapGeneratorPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app.init_resource::<World>()
+            .init_resource::<WorldGenerator>()
             .add_startup_system(generate_world.system())
-            .add_system(render_world.system());
+            .init_resource::<TerrainMaterials>()
+            .init_resource::<EnemyRegistry>()
+            .init_resource::<ResourceNodes>()
+            .init_resource::<HarvestState>()
+            .add_system(load_chunks.system())
+            .add_system(render_terrain.system())
+            .add_system(render_world.system())
+            .add_system(update_enemy_records.system())
+            .add_system(harvest_resources.system())
+            .add_system(regrow_resource_nodes.system());
     }
 }
 
@@ -34,41 +50,93 @@ pub struct World {
     generated: bool,
 }
 
-fn generate_world(mut world_map: ResMut<WorldMap>, mut rapier_config: ResMut<RapierConfiguration>) {
-    for x in 200..300 {
-        for y in 125..175 {
-            let mut tile = world_map.get_tile_mut(x, y).unwrap();
-            tile.biome = Biome::Desert;
-            if (rand::random::<u32>() % 1000) as f32 / 1000. <= ENEMY_DENSITY {
-                tile.contains_enemy = true;
-                // println!("Enemy should be spawned at {} {}", x, y)
-            }
+fn generate_world(
+    world_generator: Res<WorldGenerator>,
+    prefabs: Res<Prefabs>,
+    mut world_map: ResMut<WorldMap>,
+    mut enemy_registry: ResMut<EnemyRegistry>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+) {
+    world_map.update_loaded_chunks(TileCoordinate(0, 0), &world_generator);
+    let mut enemy_spawns = world_map.take_enemy_spawns();
+    enemy_spawns.push(TileCoordinate(10, 0));
+    register_enemy_spawns(
+        enemy_spawns,
+        &world_generator,
+        &prefabs,
+        &mut enemy_registry,
+    );
+    rapier_config.gravity = Vector::y() * 0.;
+}
+
+/// Registers an enemy on each of the tiles, of a kind picked from the spawn table of the tile's
+/// biome.
+fn register_enemy_spawns(
+    enemy_spawns: Vec<TileCoordinate>,
+    world_generator: &WorldGenerator,
+    prefabs: &Prefabs,
+    enemy_registry: &mut EnemyRegistry,
+) {
+    for enemy_spawn in enemy_spawns {
+        let biome = world_generator.biome_at(enemy_spawn.0, enemy_spawn.1);
+        let roll = world_generator.enemy_roll(enemy_spawn.0, enemy_spawn.1);
+        let enemy_type = prefabs.enemy_spawn(biome, roll);
+        enemy_registry.register(enemy_spawn, enemy_type, prefabs.enemy(enemy_type).max_hp);
+    }
+}
+
+/// Generates chunks as the camera approaches them and unloads the ones it has left behind.
+fn load_chunks(
+    mut commands: Commands,
+    world_generator: Res<WorldGenerator>,
+    prefabs: Res<Prefabs>,
+    mut world_map: ResMut<WorldMap>,
+    mut enemy_registry: ResMut<EnemyRegistry>,
+    query_camera: Query<(&WorldCamera, &Transform)>,
+) {
+    if let Some((_camera, camera_transform)) = query_camera.iter().next() {
+        let camera_tile = position_to_tile(
+            camera_transform.translation.x(),
+            camera_transform.translation.y(),
+        );
+        for entity in world_map.update_loaded_chunks(camera_tile, &world_generator) {
+            commands.despawn(entity);
         }
+        register_enemy_spawns(
+            world_map.take_enemy_spawns(),
+            &world_generator,
+            &prefabs,
+            &mut enemy_registry,
+        );
     }
-    world_map.get_tile_mut(160, 150).unwrap().contains_enemy = true;
-    rapier_config.gravity = Vector::y() * 0.;
 }
 
+#[allow(clippy::too_many_arguments)]
 fn render_world(
     mut commands: Commands,
     atlas_handles: Res<AtlasHandles>,
+    prefabs: Res<Prefabs>,
     mut world: ResMut<World>,
     mut world_map: ResMut<WorldMap>,
-    query_camera: Query<(&Camera, &Transform)>,
+    mut enemy_registry: ResMut<EnemyRegistry>,
+    mut resource_nodes: ResMut<ResourceNodes>,
+    query_camera: Query<(&WorldCamera, &Transform)>,
 ) {
     if atlas_handles.loaded() {
         if !world.generated {
             let builder_atlas_handle =
                 Handle::weak(atlas_handles.get_asset(AssetType::Builder).unwrap());
-            let builder_x = 0.;
-            let builder_y = 0.;
+            let builder_info = prefabs.builder();
+            let spawn_position = tile_to_position(builder_info.spawn_tile).translation;
+            let builder_x = spawn_position.x();
+            let builder_y = spawn_position.y();
             let builder_z = UNIT_Z;
 
             let builder_body = RigidBodyBuilder::new_dynamic()
                 .translation(builder_x, builder_y, builder_z)
                 .lock_rotations()
                 .lock_translations();
-            let builder_collider = data::get_collision_data(UnitType::Wizard);
+            let builder_collider = prefabs.collider(UnitType::Wizard);
             commands
                 .spawn(SpriteSheetComponents {
                     texture_atlas: builder_atlas_handle,
@@ -78,11 +146,13 @@ fn render_world(
                     )),
                     ..Default::default()
                 })
-                .with_bundle(AnimationBundle::new(UnitType::Wizard))
+                .with_bundle(AnimationBundle::new(UnitType::Wizard, &prefabs))
                 .with(builder_body)
                 .with(builder_collider)
-                .with(Weapon::magic_bow())
-                .with(Builder::new("Bob the builder"));
+                .with(Weapon::magic_bow(&prefabs))
+                .with(Builder::new("Bob the builder"))
+                .with(builder::starting_inventory())
+                .with(Health::new(builder_info.max_hp));
 
             world.generated = true;
         }
@@ -90,61 +160,29 @@ fn render_world(
         // can probably be assertion?
         let query_camera_iterator = &mut query_camera.iter();
         if let Some((_camera, camera_transform)) = query_camera_iterator.into_iter().next() {
-            let center_tile = world_map.center_tile();
+            // terrain is drawn per chunk by render_terrain, tiles entering view only wake enemies
+            // and show their resource nodes
             let (tiles_to_render, tiles_to_despawn) = world_map.get_tiles_for_update(
                 camera_transform.translation.x(),
                 camera_transform.translation.y(),
             );
-            for tile in tiles_to_render {
-                // println!("render {} {} as {:?}", tile.x, tile.y, tile.biome);
-                if tile.rendered_entity.is_none() {
-                    tile.rendered_entity.replace(
-                        commands
-                            .spawn(SpriteSheetComponents {
-                                texture_atlas: tile.get_biome_handle(&atlas_handles),
-                                sprite: TextureAtlasSprite::new(rand::random::<u32>() % 4),
-                                transform: tile_to_position(&center_tile, tile.x, tile.y),
-                                ..Default::default()
-                            })
-                            .current_entity()
-                            .unwrap(),
-                    );
-                }
-                if tile.contains_enemy {
-                    let enemy_atlas_handle =
-                        Handle::weak(atlas_handles.get_asset(AssetType::Enemy).unwrap());
-                    // TODO: this should use UNIT_Z instead of 0.
-                    let enemy_transform = tile_to_position(&center_tile, tile.x, tile.y);
-                    let enemy_entity = commands
-                        .spawn(SpriteSheetComponents {
-                            texture_atlas: enemy_atlas_handle,
-                            sprite: TextureAtlasSprite::new(7),
-                            transform: enemy_transform,
-                            ..Default::default()
-                        })
-                        .with_bundle(AnimationBundle::new(UnitType::Enemy))
-                        .with(Enemy::generic_enemy())
-                        .current_entity()
-                        .unwrap();
-
-                    let enemy_body = RigidBodyBuilder::new_dynamic()
-                        .translation(
-                            enemy_transform.translation.x(),
-                            enemy_transform.translation.y(),
-                            enemy_transform.translation.z(),
-                        )
-                        .lock_rotations()
-                        .lock_translations()
-                        .mass(1000., false);
-                    let enemy_collider = data::get_collision_data(UnitType::Enemy)
-                        .user_data(enemy_entity.to_bits() as u128);
-                    commands.insert(enemy_entity, (enemy_body, enemy_collider));
-                }
+            for tile_coordinate in tiles_to_despawn {
+                hide_resource_node(&mut commands, &mut resource_nodes, tile_coordinate);
             }
-            for tile in tiles_to_despawn {
-                let entity = tile.rendered_entity.unwrap();
-                commands.despawn(entity);
-                tile.rendered_entity.take();
+            for tile_coordinate in tiles_to_render {
+                show_resource_node(
+                    &mut commands,
+                    &atlas_handles,
+                    &prefabs,
+                    &mut resource_nodes,
+                    &world_map,
+                    tile_coordinate,
+                );
+                for enemy_record in enemy_registry.wake_at(tile_coordinate) {
+                    let enemy_entity =
+                        enemy::spawn_enemy(&mut commands, &atlas_handles, &prefabs, &enemy_record);
+                    enemy_registry.set_spawned(enemy_record.id, enemy_entity);
+                }
             }
         }
     }